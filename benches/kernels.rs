@@ -0,0 +1,52 @@
+#[macro_use]
+extern crate criterion;
+extern crate num;
+extern crate fractal;
+
+use std::hint::black_box;
+use criterion::Criterion;
+use num::complex::Complex64;
+use fractal::prelude::*;
+use fractal::escape_time::EscapeTime;
+use fractal::pixel_band::PixelBand;
+use fractal::window::Window;
+use fractal::window_iterator::WindowBorderIterator;
+use fractal::recursive_mandelbrot_engine::fill_window;
+
+
+fn escape_time_benchmark(c: &mut Criterion) {
+    let inside = Complex64::new(0.0, 0.0);
+    let boundary = Complex64::new(-0.74548, 0.11669);
+    let outside = Complex64::new(0.5, 0.5);
+
+    c.bench_function("escape_time inside", |b| b.iter(|| black_box(inside).escape_time(255)));
+    c.bench_function("escape_time boundary", |b| b.iter(|| black_box(boundary).escape_time(255)));
+    c.bench_function("escape_time outside", |b| b.iter(|| black_box(outside).escape_time(255)));
+}
+
+
+fn window_border_iterator_benchmark(c: &mut Criterion) {
+    let window = Window::new(0, 0, 256, 256);
+
+    c.bench_function("WindowBorderIterator 256x256", |b| {
+        b.iter(|| WindowBorderIterator::new(black_box(&window)).fold(0, |sum, pixel| sum + pixel.x + pixel.y))
+    });
+}
+
+
+fn fill_window_benchmark(c: &mut Criterion) {
+    let mandelbrot = Mandelbrot::new(Region::new_for_type(RegionType::Default), 256, 256, 255);
+    let window = Window::new(0, 0, 256, 256);
+    let mut pixels: Vec<u8> = vec![0; 256 * 256];
+
+    c.bench_function("fill_window 256x256", |b| {
+        b.iter(|| {
+            let mut pixel_band = PixelBand::new(&mut pixels, 0);
+            fill_window(&mandelbrot, black_box(&window), 42, &mut pixel_band);
+        })
+    });
+}
+
+
+criterion_group!(benches, escape_time_benchmark, window_border_iterator_benchmark, fill_window_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,84 @@
+#ifndef FRACTAL_H
+#define FRACTAL_H
+
+/* Generated from src/ffi.rs by cbindgen, run the tests with FRACTAL_UPDATE_HEADER set to update */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum FractalStatus {
+  FRACTAL_OK = 0,
+  FRACTAL_NULL_POINTER = 1,
+  FRACTAL_INVALID_ARGUMENT = 2,
+  FRACTAL_BUFFER_TOO_SMALL = 3,
+  FRACTAL_UNKNOWN_NAME = 4,
+  FRACTAL_PANIC = 5,
+} FractalStatus;
+
+typedef enum FractalEngineType {
+  FRACTAL_ENGINE_SIMPLE = 0,
+  FRACTAL_ENGINE_RECURSIVE = 1,
+} FractalEngineType;
+
+typedef struct FractalEngine FractalEngine;
+
+typedef struct FractalRegion {
+  double min_re;
+  double max_re;
+  double min_im;
+  double max_im;
+} FractalRegion;
+
+typedef struct FractalParams {
+  struct FractalRegion region;
+  uint32_t width;
+  uint32_t height;
+  uint8_t max_iterations;
+  double rotation;
+  bool julia;
+  double julia_re;
+  double julia_im;
+  const struct FractalEngine *engine;
+} FractalParams;
+
+typedef struct FractalStats {
+  uint64_t escape_time_calls;
+  uint64_t iterations;
+  uint64_t pixels_inside;
+  uint64_t pixels_outside;
+  uint64_t windows_filled;
+  uint64_t windows_calculated;
+} FractalStats;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+struct FractalEngine *fractal_engine_new(uint32_t engine_type,
+                                         bool in_parallel,
+                                         uint32_t band_height);
+
+void fractal_engine_free(struct FractalEngine *engine);
+
+enum FractalStatus fractal_region_for_name(const char *name, struct FractalRegion *region);
+
+enum FractalStatus fractal_region_for_center(double re,
+                                             double im,
+                                             double radius,
+                                             struct FractalRegion *region);
+
+enum FractalStatus fractal_render(const struct FractalParams *params,
+                                  uint8_t *pixels,
+                                  size_t pixels_len,
+                                  struct FractalStats *stats);
+
+const char *fractal_status_message(uint32_t status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FRACTAL_H */
@@ -0,0 +1,522 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use gif;
+use num::complex::Complex64;
+use png;
+use rayon::prelude::*;
+
+use errors::*;
+use config::Config;
+use fractal::coloring;
+use fractal::prelude::*;
+use output_format::STDOUT_FILENAME;
+use png_file;
+use render_parameters::{RenderParameters, SOFTWARE_KEYWORD, software};
+use super::{elapsed_milliseconds, parsed_resolution, parsed_max_iterations, parsed_region, parsed_center_and_radius};
+
+
+// Below this number of pixels a single frame does not keep all threads busy, so whole frames are distributed instead
+const PER_FRAME_PARALLEL_PIXELS: u64 = 1024 * 1024;
+const CHROMA_NEUTRAL: u8 = 128;
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Easing {
+    Linear,
+    Smooth,
+}
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum FrameParallelism {
+    Frames,
+    Pixels,
+}
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Frame {
+    pub center: Complex64,
+    pub radius: f64,
+    pub max_iterations: u8,
+}
+
+
+// Playback of Y4M streams, animated GIFs and APNGs, and the gray levels of GIFs
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct AnimationSettings {
+    pub fps: u32,
+    // 0 loops forever
+    pub loops: u32,
+    pub colors: u16,
+    pub dither: bool,
+}
+
+
+// Numbered PNG files in a directory, a Y4M stream, an animated GIF or an APNG
+pub struct FrameOutput {
+    width: u32,
+    height: u32,
+    target: FrameTarget,
+}
+
+
+enum FrameTarget {
+    Png(String),
+    Y4m(Box<dyn Write>),
+    Gif { encoder: gif::Encoder<Box<dyn Write>>, delay: u16, colors: u16, dither: bool },
+    Apng(png::Writer<Box<dyn Write>>),
+}
+
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("animate")
+        .about("Renders an exponential zoom from a start region to a target as a sequence of frames")
+        .arg(Arg::with_name("type")
+            .short("t")
+            .long("type")
+            .help("Sets the start region by a region type or preset (see: fractal presets list)")
+            .takes_value(true)
+            .value_name("TYPE")
+            .default_value("Default"))
+        .arg(Arg::with_name("center-and-radius")
+            .short("c")
+            .long("center-and-radius")
+            .conflicts_with("type")
+            .help("Sets the start region by a center and radius (format: <real>/<imag>@radius)")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("CENTER-AND-RADIUS"))
+        .arg(Arg::with_name("target")
+            .long("target")
+            .help("Specifies the center and radius the zoom ends at (format: <real>/<imag>@radius)")
+            .required(true)
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("TARGET"))
+        .arg(Arg::with_name("frames")
+            .short("n")
+            .long("frames")
+            .help("Number of frames")
+            .takes_value(true)
+            .value_name("FRAMES")
+            .default_value("100"))
+        .arg(Arg::with_name("fps")
+            .long("fps")
+            .help("Frames per second of the Y4M stream, GIF or APNG")
+            .takes_value(true)
+            .value_name("FPS")
+            .default_value("30"))
+        .arg(Arg::with_name("easing")
+            .long("easing")
+            .help("Progress of the zoom over time (values: smooth, linear)")
+            .takes_value(true)
+            .value_name("EASING")
+            .default_value("smooth"))
+        .arg(Arg::with_name("max-iterations")
+            .short("i")
+            .long("max-iterations")
+            .help("Sets max iterations of the first frame")
+            .takes_value(true)
+            .value_name("MAX-ITERATIONS")
+            .default_value("100"))
+        .arg(Arg::with_name("iterations-per-octave")
+            .long("iterations-per-octave")
+            .help("Max iterations added per halving of the radius (at most 255 at the target)")
+            .takes_value(true)
+            .value_name("ITERATIONS")
+            .default_value("8"))
+        .arg(Arg::with_name("resolution")
+            .short("r")
+            .long("resolution")
+            .help("Specifies the resolution of the frames (format: <width>x<height>)")
+            .value_name("RESOLUTION")
+            .default_value("640x480"))
+        .arg(Arg::with_name("band-height")
+            .short("b")
+            .long("band-height")
+            .help("Specifies number of rows per band (only sensible in case of the recursive engine)")
+            .value_name("BAND-HEIGHT")
+            .default_value("64"))
+        .arg(Arg::with_name("engine")
+            .short("e")
+            .long("engine")
+            .help("Choose engine to to use (values: Default, Recursive)")
+            .takes_value(true)
+            .value_name("ENGINE")
+            .default_value("Default"))
+        .arg(Arg::with_name("frame-parallelism")
+            .long("frame-parallelism")
+            .help("Renders several frames at once or each frame in parallel (values: auto, frames, pixels)")
+            .takes_value(true)
+            .value_name("FRAME-PARALLELISM")
+            .default_value("auto"))
+        .args(&frame_output_arguments())
+}
+
+
+// The output of the frames and its settings, shared by all subcommands rendering frames
+pub fn frame_output_arguments<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .help("Directory for numbered PNG frames, a .y4m file (- writes a Y4M stream to stdout), an animated .gif or an .apng/.png")
+            .required(true)
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("OUTPUT"),
+        Arg::with_name("loops")
+            .long("loops")
+            .help("Number of times a GIF or APNG is played (0: forever)")
+            .takes_value(true)
+            .value_name("LOOPS")
+            .default_value("0"),
+        Arg::with_name("colors")
+            .long("colors")
+            .help("Number of gray levels of a GIF (2 to 256)")
+            .takes_value(true)
+            .value_name("COLORS")
+            .default_value("256"),
+        Arg::with_name("no-dither")
+            .long("no-dither")
+            .help("Rounds the gray levels of a GIF to the nearest color instead of dithering them"),
+    ]
+}
+
+
+pub fn frame_output(arguments: &ArgMatches, width: u32, height: u32, frame_count: usize, fps: u32) -> Result<FrameOutput> {
+    let settings = AnimationSettings {
+        fps,
+        loops: arguments.value_of("loops").unwrap().parse::<u32>().chain_err(|| "invalid number of loops")?,
+        colors: arguments.value_of("colors").unwrap().parse::<u16>().chain_err(|| "invalid number of colors")?,
+        dither: !arguments.is_present("no-dither"),
+    };
+
+    FrameOutput::new(arguments.value_of("output").unwrap(), width, height, frame_count, &settings)
+}
+
+
+pub fn run(arguments: &ArgMatches, config: &Config) -> Result<()> {
+    let start_region = parsed_region(config, arguments.value_of("type"), arguments.value_of("center-and-radius")).chain_err(|| "parsing start region failed")?;
+    let target_region = parsed_center_and_radius(arguments.value_of("target").unwrap()).chain_err(|| "parsing target failed")?;
+    let frame_count = arguments.value_of("frames").unwrap().parse::<u32>().chain_err(|| "invalid number of frames")?;
+    let fps = arguments.value_of("fps").unwrap().parse::<u32>().chain_err(|| "invalid fps")?;
+    let easing = parsed_easing(arguments.value_of("easing").unwrap()).chain_err(|| "parsing easing failed")?;
+    let max_iterations = parsed_max_iterations(arguments.value_of("max-iterations").unwrap()).chain_err(|| "parsing max iterations failed")?;
+    let iterations_per_octave = arguments.value_of("iterations-per-octave").unwrap().parse::<f64>().chain_err(|| "invalid iterations per octave")?;
+    let (width, height) = parsed_resolution(arguments.value_of("resolution").unwrap()).chain_err(|| "parsing resolution failed")?;
+    let band_height = arguments.value_of("band-height").unwrap().parse::<u32>().chain_err(|| "invalid band size")?;
+    let engine_type = arguments.value_of("engine").unwrap();
+
+    if frame_count == 0 || fps == 0 {
+        bail!("frames and fps have to be positive");
+    }
+
+    let frame_parallelism = parsed_frame_parallelism(arguments.value_of("frame-parallelism").unwrap(), width, height).chain_err(|| "parsing frame parallelism failed")?;
+
+    // Frames beyond 255 iterations would silently render with fewer than asked for
+    let target_iterations = zoomed_iterations(region_radius(&start_region), region_radius(&target_region), max_iterations, iterations_per_octave);
+
+    if target_iterations > u8::MAX as f64 {
+        bail!("the target needs {} max iterations, at most {} are supported (lower --max-iterations or --iterations-per-octave)",
+              target_iterations, u8::MAX);
+    }
+
+    let frames = zoom_frames(region_center(&start_region), region_radius(&start_region), region_center(&target_region), region_radius(&target_region),
+                             frame_count, easing, max_iterations, iterations_per_octave);
+
+    let parameters_of_frame = |frame: &Frame| RenderParameters {
+        region: Region::new_for_center(frame.center, frame.radius),
+        width,
+        height,
+        max_iterations: frame.max_iterations,
+        engine_type: engine_type.to_string(),
+        in_parallel: frame_parallelism == FrameParallelism::Pixels,
+        band_height,
+    };
+
+    // Fail early instead of after the first batch of frames
+    parameters_of_frame(&frames[0]).engine().chain_err(|| "parsing engine type failed")?;
+
+    let mut output = frame_output(arguments, width, height, frames.len(), fps)?;
+
+    render_frames(frames.len(), frame_parallelism, &mut output, |i| {
+        let parameters = parameters_of_frame(&frames[i]);
+        let (pixels, _) = parameters.mandelbrot().calculate(&*parameters.engine()?);
+
+        Ok((pixels, parameters.to_text_chunks()))
+    })?;
+
+    output.finish()
+}
+
+
+pub fn parsed_frame_parallelism(frame_parallelism: &str, width: u32, height: u32) -> Result<FrameParallelism> {
+    match frame_parallelism {
+        "frames" => Ok(FrameParallelism::Frames),
+        "pixels" => Ok(FrameParallelism::Pixels),
+        "auto" if (width as u64) * (height as u64) < PER_FRAME_PARALLEL_PIXELS => Ok(FrameParallelism::Frames),
+        "auto" => Ok(FrameParallelism::Pixels),
+        _ => bail!("unsupported frame parallelism"),
+    }
+}
+
+
+// Renders the frames (pixels and text chunks) in batches and writes them in order
+pub fn render_frames<R>(frame_count: usize, frame_parallelism: FrameParallelism, output: &mut FrameOutput, render_frame: R) -> Result<()>
+    where R: Fn(usize) -> Result<(Vec<u8>, Vec<(String, String)>)> + Sync
+{
+    let batch_size = match frame_parallelism {
+        FrameParallelism::Frames => rayon::current_num_threads(),
+        FrameParallelism::Pixels => 1,
+    };
+    let animation_start = Instant::now();
+    let frame_indices: Vec<usize> = (0..frame_count).collect();
+
+    for batch in frame_indices.chunks(batch_size) {
+        let rendered = batch.par_iter()
+            .map(|&i| render_frame(i))
+            .collect::<Result<Vec<(Vec<u8>, Vec<(String, String)>)>>>()?;
+
+        for (&i, (pixels, text_chunks)) in batch.iter().zip(rendered.iter()) {
+            output.write_frame(i, pixels, text_chunks)?;
+
+            info!(event = "frame_finished", frame = i; "frame {} of {} finished", i + 1, frame_count);
+        }
+    }
+
+    info!(event = "animation_finished", frames = frame_count, elapsed_ms = elapsed_milliseconds(animation_start);
+          "animation finished");
+
+    Ok(())
+}
+
+
+fn parsed_easing(easing: &str) -> Result<Easing> {
+    match easing {
+        "smooth" => Ok(Easing::Smooth),
+        "linear" => Ok(Easing::Linear),
+        _ => bail!("unsupported easing")
+    }
+}
+
+
+pub fn eased(progress: f64, easing: Easing) -> f64 {
+    match easing {
+        Easing::Linear => progress,
+        Easing::Smooth => progress * progress * (3.0 - 2.0 * progress),
+    }
+}
+
+
+pub fn region_center(region: &Region) -> Complex64 {
+    Complex64::new(region.min_re() + region.width() / 2.0, region.min_im() + region.height() / 2.0)
+}
+
+
+pub fn region_radius(region: &Region) -> f64 {
+    region.width().max(region.height()) / 2.0
+}
+
+
+// The radius shrinks exponentially, so every frame zooms by the same factor (apart from easing). The center
+// approaches the target in proportion to the radius, which keeps the target at a fixed spot of the view.
+#[allow(clippy::too_many_arguments)]
+pub fn zoom_frames(start_center: Complex64, start_radius: f64, target_center: Complex64, target_radius: f64, frame_count: u32,
+                   easing: Easing, start_iterations: u8, iterations_per_octave: f64) -> Vec<Frame> {
+    (0..frame_count)
+        .map(|i| {
+            let progress = if frame_count > 1 { i as f64 / (frame_count - 1) as f64 } else { 1.0 };
+            let depth = eased(progress, easing);
+            let radius = start_radius * (target_radius / start_radius).powf(depth);
+
+            let remaining = if start_radius != target_radius {
+                (radius - target_radius) / (start_radius - target_radius)
+            } else {
+                1.0 - depth
+            };
+
+            let max_iterations = zoomed_iterations(start_radius, radius, start_iterations, iterations_per_octave).min(255.0) as u8;
+
+            Frame { center: target_center + (start_center - target_center) * remaining, radius, max_iterations }
+        })
+        .collect()
+}
+
+
+// Max iterations at the radius, raised by iterations_per_octave for every halving of the start radius
+fn zoomed_iterations(start_radius: f64, radius: f64, start_iterations: u8, iterations_per_octave: f64) -> f64 {
+    let octaves = (start_radius / radius).log2().max(0.0);
+
+    (start_iterations as f64 + octaves * iterations_per_octave).round()
+}
+
+
+impl FrameOutput {
+    pub fn new(output: &str, width: u32, height: u32, frame_count: usize, settings: &AnimationSettings) -> Result<FrameOutput> {
+        let extension = Path::new(output).extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_lowercase());
+
+        let target = match (output, extension.as_deref()) {
+            (STDOUT_FILENAME, _) => FrameTarget::Y4m(Box::new(BufWriter::new(io::stdout()))),
+            (_, Some("y4m")) => FrameTarget::Y4m(Box::new(BufWriter::new(File::create(output)?))),
+            (_, Some("gif")) => {
+                if settings.colors < 2 || settings.colors > 256 {
+                    bail!("a GIF has 2 to 256 colors");
+                }
+
+                if width > u16::MAX as u32 || height > u16::MAX as u32 {
+                    bail!("frames are too large for a GIF");
+                }
+
+                let stream: Box<dyn Write> = Box::new(BufWriter::new(File::create(output)?));
+                let mut encoder = gif::Encoder::new(stream, width as u16, height as u16, &coloring::gray_palette(settings.colors))?;
+                encoder.set_repeat(if settings.loops == 0 { gif::Repeat::Infinite } else { gif::Repeat::Finite(settings.loops.min(u16::MAX as u32) as u16) })?;
+
+                // GIF delays are in hundredths of a second
+                let delay = (100.0 / settings.fps as f64).round().max(1.0) as u16;
+
+                FrameTarget::Gif { encoder, delay, colors: settings.colors, dither: settings.dither }
+            },
+            (_, Some("apng")) | (_, Some("png")) => {
+                if settings.fps > u16::MAX as u32 {
+                    bail!("fps too high for an APNG");
+                }
+
+                let stream: Box<dyn Write> = Box::new(BufWriter::new(File::create(output)?));
+                let text_chunks = vec![(SOFTWARE_KEYWORD.to_string(), software())];
+
+                FrameTarget::Apng(png_file::animated_writer(stream, width, height, frame_count as u32, settings.loops, settings.fps as u16, &text_chunks)?)
+            },
+            _ => {
+                fs::create_dir_all(output)?;
+                FrameTarget::Png(output.to_string())
+            },
+        };
+
+        let mut frame_output = FrameOutput { width, height, target };
+
+        if let FrameTarget::Y4m(ref mut stream) = frame_output.target {
+            // Full range gray frames with neutral chroma, which every encoder accepts
+            writeln!(stream, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C420jpeg XCOLORRANGE=FULL", width, height, settings.fps)?;
+        }
+
+        Ok(frame_output)
+    }
+
+
+    // Frames have to be written in order
+    pub fn write_frame(&mut self, frame_index: usize, pixels: &[u8], text_chunks: &[(String, String)]) -> Result<()> {
+        match self.target {
+            FrameTarget::Png(ref directory) => {
+                let path = Path::new(directory).join(format!("frame_{:05}.png", frame_index + 1));
+                let file = BufWriter::new(File::create(path)?);
+
+                png_file::write(file, self.width, self.height, png::ColorType::Grayscale, png::BitDepth::Eight, pixels, text_chunks)
+            },
+            FrameTarget::Y4m(ref mut stream) => {
+                let chroma_size = (self.width.div_ceil(2) as usize) * (self.height.div_ceil(2) as usize);
+                let chroma = vec![CHROMA_NEUTRAL; chroma_size];
+
+                stream.write_all(b"FRAME\n")?;
+                stream.write_all(pixels)?;
+                stream.write_all(&chroma)?;
+                stream.write_all(&chroma)?;
+
+                Ok(())
+            },
+            FrameTarget::Gif { ref mut encoder, delay, colors, dither } => {
+                let frame = gif::Frame {
+                    delay,
+                    width: self.width as u16,
+                    height: self.height as u16,
+                    buffer: coloring::quantized(pixels, self.width as usize, colors, dither).into(),
+                    ..gif::Frame::default()
+                };
+
+                Ok(encoder.write_frame(&frame)?)
+            },
+            FrameTarget::Apng(ref mut writer) => Ok(writer.write_image_data(pixels)?),
+        }
+    }
+
+
+    pub fn finish(self) -> Result<()> {
+        match self.target {
+            FrameTarget::Png(_) => {},
+            FrameTarget::Y4m(mut stream) => stream.flush()?,
+            FrameTarget::Gif { encoder, .. } => encoder.into_inner()?.flush()?,
+            FrameTarget::Apng(writer) => writer.finish()?,
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zoom_frames_start_and_end_at_given_views() {
+        // arrange
+        let start = Complex64::new(-0.5, 0.0);
+        let target = Complex64::new(-0.74548, 0.11669);
+
+        // act
+        let frames = zoom_frames(start, 1.5, target, 0.01276, 50, Easing::Smooth, 100, 8.0);
+
+        // assert
+        assert_eq!(frames.len(), 50);
+        assert!((frames[0].center - start).norm() < 1e-12);
+        assert_eq!(frames[0].radius, 1.5);
+        assert_eq!(frames[0].max_iterations, 100);
+        assert!((frames[49].center - target).norm() < 1e-12);
+        assert!((frames[49].radius - 0.01276).abs() < 1e-12);
+    }
+
+    #[test]
+    fn zoom_frames_shrink_radius_and_raise_iterations_monotonically() {
+        // act
+        let frames = zoom_frames(Complex64::new(0.0, 0.0), 2.0, Complex64::new(0.3, 0.0), 1e-6, 30, Easing::Linear, 50, 20.0);
+
+        // assert
+        for pair in frames.windows(2) {
+            assert!(pair[1].radius < pair[0].radius);
+            assert!(pair[1].max_iterations >= pair[0].max_iterations);
+        }
+        assert_eq!(frames[29].max_iterations, 255);
+    }
+
+    #[test]
+    fn zoomed_iterations_are_not_capped() {
+        // act & assert
+        assert_eq!(zoomed_iterations(1.0, 1.0, 100, 20.0), 100.0);
+        assert_eq!(zoomed_iterations(1.0, 1.0 / 1024.0, 100, 20.0), 300.0);
+    }
+
+    #[test]
+    fn linear_zoom_has_constant_zoom_factor() {
+        // act
+        let frames = zoom_frames(Complex64::new(0.0, 0.0), 1.0, Complex64::new(0.0, 0.0), 1.0 / 1024.0, 11, Easing::Linear, 100, 0.0);
+
+        // assert
+        for pair in frames.windows(2) {
+            assert!((pair[0].radius / pair[1].radius - 2.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn smooth_easing_starts_and_ends_slowly() {
+        // act & assert
+        assert_eq!(eased(0.0, Easing::Smooth), 0.0);
+        assert_eq!(eased(0.5, Easing::Smooth), 0.5);
+        assert_eq!(eased(1.0, Easing::Smooth), 1.0);
+        assert!(eased(0.1, Easing::Smooth) < 0.1);
+    }
+}
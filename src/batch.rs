@@ -0,0 +1,260 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::time::Instant;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use rayon::prelude::*;
+use serde_json;
+use toml;
+
+use errors::*;
+use config::Config;
+use render_cache::RenderCache;
+use render_parameters::RenderParameters;
+use super::{create_mandelbrot_file, elapsed_milliseconds, parsed_resolution, parsed_region, parsed_output_format};
+
+
+const DEFAULT_BAND_HEIGHT: u32 = 64;
+
+
+#[derive(Debug, Deserialize)]
+struct BatchFile {
+    #[serde(rename = "job", alias = "jobs")]
+    jobs: Vec<Job>,
+}
+
+
+// Settings left out fall back to the config files and the defaults of the render command
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Job {
+    pub name: Option<String>,
+    // Region type, preset or <real>/<imag>@radius
+    pub region: String,
+    pub resolution: Option<String>,
+    pub engine: Option<String>,
+    pub max_iterations: Option<u8>,
+    #[serde(default)]
+    pub parallel: bool,
+    pub band_height: Option<u32>,
+    pub output: String,
+    pub format: Option<String>,
+}
+
+
+struct JobResult {
+    label: String,
+    output: String,
+    elapsed_ms: f64,
+    error: Option<String>,
+}
+
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("batch")
+        .about("Renders the images of a job file (TOML with [[job]] tables, or JSON lines) sharing one thread pool")
+        .arg(Arg::with_name("job-file")
+            .help("Job file, each job has a region, an output and optionally a name, resolution, engine, max_iterations, parallel, band_height and format")
+            .required(true)
+            .value_name("JOB-FILE"))
+        .arg(Arg::with_name("cache")
+            .long("cache")
+            .help("Reuses the iterations of identical earlier renders from the on-disk cache, or stores them there"))
+}
+
+
+pub fn run(arguments: &ArgMatches, config: &Config) -> Result<()> {
+    let jobs = read_jobs(arguments.value_of("job-file").unwrap()).chain_err(|| "reading job file failed")?;
+    let cache = if arguments.is_present("cache") { Some(RenderCache::open().chain_err(|| "opening render cache failed")?) } else { None };
+
+    let batch_start = Instant::now();
+
+    // Jobs are distributed over the thread pool, a job rendering in parallel shares it with the others
+    let results: Vec<JobResult> = jobs.par_iter()
+        .enumerate()
+        .map(|(i, job)| {
+            let label = job.name.clone().unwrap_or_else(|| format!("#{}", i + 1));
+            let job_start = Instant::now();
+            let result = run_job(job, config, cache.as_ref());
+
+            if let Err(ref e) = result {
+                error!(event = "job_failed", job = label.as_str(); "job {} failed: {}", label, error_message(e));
+            }
+
+            JobResult {
+                label,
+                output: job.output.clone(),
+                elapsed_ms: elapsed_milliseconds(job_start),
+                error: result.err().map(|e| error_message(&e)),
+            }
+        })
+        .collect();
+
+    let failures = results.iter().filter(|result| result.error.is_some()).count();
+
+    info!(event = "batch_finished", jobs = results.len(), failures = failures, elapsed_ms = elapsed_milliseconds(batch_start);
+          "batch finished");
+
+    print_summary(&results);
+
+    if failures > 0 {
+        bail!("{} of {} jobs failed", failures, results.len());
+    }
+
+    Ok(())
+}
+
+
+pub fn read_jobs(filename: &str) -> Result<Vec<Job>> {
+    let mut text = String::new();
+    File::open(filename)?.read_to_string(&mut text)?;
+
+    let extension = Path::new(filename).extension().and_then(|extension| extension.to_str()).unwrap_or("").to_lowercase();
+
+    if extension == "jsonl" || extension == "ndjson" {
+        return text.lines()
+            .enumerate()
+            .filter(|&(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| serde_json::from_str(line).chain_err(|| format!("invalid job in line {}", i + 1)))
+            .collect();
+    }
+
+    let batch_file: BatchFile = toml::from_str(&text)?;
+
+    Ok(batch_file.jobs)
+}
+
+
+// A panicking job fails on its own, the other jobs keep running and are reported
+fn run_job(job: &Job, config: &Config, cache: Option<&RenderCache>) -> Result<()> {
+    match panic::catch_unwind(AssertUnwindSafe(|| render_job(job, config, cache))) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = payload.downcast_ref::<&str>().map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown cause".to_string());
+
+            bail!("job panicked: {}", message)
+        },
+    }
+}
+
+
+fn render_job(job: &Job, config: &Config, cache: Option<&RenderCache>) -> Result<()> {
+    let parameters = job_parameters(job, config)?;
+    let output_format = parsed_output_format(job.format.as_deref(), &job.output).chain_err(|| "parsing output format failed")?;
+
+    if let Some(directory) = Path::new(&job.output).parent() {
+        fs::create_dir_all(directory)?;
+    }
+
+    create_mandelbrot_file(&parameters, &job.output, output_format, None, cache, None)
+}
+
+
+pub fn job_parameters(job: &Job, config: &Config) -> Result<RenderParameters> {
+    let region = if job.region.contains('@') {
+        parsed_region(config, None, Some(&job.region))
+    } else {
+        parsed_region(config, Some(&job.region), None)
+    };
+    let (width, height) = parsed_resolution(job.resolution.as_ref().map_or(config.resolution(), |resolution| resolution.as_str()))
+        .chain_err(|| "parsing resolution failed")?;
+    Ok(RenderParameters {
+        region: region.chain_err(|| "parsing region failed")?,
+        width,
+        height,
+        max_iterations: job.max_iterations.unwrap_or(config.max_iterations()),
+        engine_type: job.engine.as_ref().map_or(config.engine(), |engine| engine.as_str()).to_string(),
+        in_parallel: job.parallel,
+        band_height: job.band_height.unwrap_or(DEFAULT_BAND_HEIGHT),
+    })
+}
+
+
+fn error_message(e: &Error) -> String {
+    e.iter().map(|e| e.to_string()).collect::<Vec<String>>().join(": ")
+}
+
+
+fn print_summary(results: &[JobResult]) {
+    let label_width = results.iter().map(|result| result.label.len()).max().unwrap_or(0).max("job".len());
+    let output_width = results.iter().map(|result| result.output.len()).max().unwrap_or(0).max("output".len());
+
+    println!("{:<lw$}  {:<ow$}  {:>9}  status", "job", "output", "time [ms]", lw = label_width, ow = output_width);
+
+    for result in results {
+        println!("{:<lw$}  {:<ow$}  {:>9.0}  {}",
+                 result.label,
+                 result.output,
+                 result.elapsed_ms,
+                 result.error.as_ref().map_or("ok".to_string(), |error| format!("failed: {}", error)),
+                 lw = label_width,
+                 ow = output_width);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toml_jobs_are_parsed() {
+        // arrange
+        let text = r#"
+            [[job]]
+            name = "sea horse"
+            region = "SeaHorseValley"
+            resolution = "1920x1080"
+            engine = "Recursive"
+            parallel = true
+            output = "wallpapers/sea-horse.png"
+
+            [[job]]
+            region = "-0.088/0.654@0.0012"
+            max_iterations = 200
+            output = "spiral.tiff"
+        "#;
+
+        // act
+        let batch_file: BatchFile = toml::from_str(text).unwrap();
+
+        // assert
+        assert_eq!(batch_file.jobs.len(), 2);
+        assert_eq!(batch_file.jobs[0].name, Some("sea horse".to_string()));
+        assert!(batch_file.jobs[0].parallel);
+        assert_eq!(batch_file.jobs[1].max_iterations, Some(200));
+        assert!(!batch_file.jobs[1].parallel);
+    }
+
+    #[test]
+    fn job_parameters_fall_back_to_config() {
+        // arrange
+        let job: Job = serde_json::from_str(r#"{"region": "-0.5/0@1.5", "engine": "Recursive", "output": "default.png"}"#).unwrap();
+        let config: Config = toml::from_str("resolution = \"320x200\"\nmax_iterations = 100").unwrap();
+
+        // act
+        let parameters = job_parameters(&job, &config).unwrap();
+
+        // assert
+        assert_eq!((parameters.width, parameters.height), (320, 200));
+        assert_eq!(parameters.max_iterations, 100);
+        assert_eq!(parameters.engine_type, "Recursive");
+        assert_eq!(parameters.band_height, DEFAULT_BAND_HEIGHT);
+    }
+
+    #[test]
+    fn job_with_zero_resolution_is_rejected() {
+        // arrange
+        let job: Job = serde_json::from_str(r#"{"region": "Default", "resolution": "0x10", "engine": "Recursive", "parallel": true, "output": "zero.png"}"#).unwrap();
+
+        // act
+        let result = run_job(&job, &Config::default(), None);
+
+        // assert
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,306 @@
+use std::fs::File;
+use std::io::Write;
+use std::time::Instant;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use errors::*;
+use config::Config;
+use fractal::prelude::*;
+use super::{parsed_resolution, parsed_max_iterations, parsed_region, parsed_center_and_radius, parsed_engine};
+
+
+struct BenchmarkCase {
+    region_name: String,
+    region: Region,
+    engine_type: String,
+    in_parallel: bool,
+    band_height: Option<u32>,
+}
+
+
+struct BenchmarkResult {
+    case: BenchmarkCase,
+    median_ms: f64,
+    min_ms: f64,
+    max_ms: f64,
+    speedup: f64,
+}
+
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("bench")
+        .about("Compares the performance of engines and settings")
+        .arg(Arg::with_name("engines")
+            .long("engines")
+            .help("Comma separated list of engines (values: Default, Recursive)")
+            .takes_value(true)
+            .value_name("ENGINES")
+            .default_value("Default,Recursive"))
+        .arg(Arg::with_name("modes")
+            .long("modes")
+            .help("Comma separated list of execution modes (values: serial, parallel)")
+            .takes_value(true)
+            .value_name("MODES")
+            .default_value("serial,parallel"))
+        .arg(Arg::with_name("band-heights")
+            .long("band-heights")
+            .help("Comma separated list of band heights (only used for parallel execution of recursive engine)")
+            .takes_value(true)
+            .value_name("BAND-HEIGHTS")
+            .default_value("64"))
+        .arg(Arg::with_name("regions")
+            .long("regions")
+            .help("Comma separated list of region types or centers and radii (i.e. SeaHorseValley,-0.74548/0.11669@0.01276)")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("REGIONS")
+            .default_value("Default,SeaHorseValley"))
+        .arg(Arg::with_name("max-iterations")
+            .short("i")
+            .long("max-iterations")
+            .help("Sets max iterations")
+            .takes_value(true)
+            .value_name("MAX-ITERATIONS")
+            .default_value("255"))
+        .arg(Arg::with_name("resolution")
+            .short("r")
+            .long("resolution")
+            .help("Specifies the resolution of the images (format: <width>x<height>)")
+            .value_name("RESOLUTION")
+            .default_value("640x480"))
+        .arg(Arg::with_name("warmup")
+            .long("warmup")
+            .help("Number of untimed runs per case")
+            .takes_value(true)
+            .value_name("WARMUP")
+            .default_value("1"))
+        .arg(Arg::with_name("repetitions")
+            .long("repetitions")
+            .help("Number of timed runs per case")
+            .takes_value(true)
+            .value_name("REPETITIONS")
+            .default_value("5"))
+        .arg(Arg::with_name("csv")
+            .long("csv")
+            .help("Writes the results as CSV to the given file")
+            .takes_value(true)
+            .value_name("CSV-FILENAME"))
+}
+
+
+pub fn run(arguments: &ArgMatches, config: &Config) -> Result<()> {
+    let (width, height) = parsed_resolution(arguments.value_of("resolution").unwrap()).chain_err(|| "parsing resolution failed")?;
+    let max_iterations = parsed_max_iterations(arguments.value_of("max-iterations").unwrap()).chain_err(|| "parsing max iterations failed")?;
+    let warmup = arguments.value_of("warmup").unwrap().parse::<u32>().chain_err(|| "invalid warmup")?;
+    let repetitions = arguments.value_of("repetitions").unwrap().parse::<u32>().chain_err(|| "invalid repetitions")?;
+
+    if repetitions == 0 {
+        bail!("at least one repetition is required");
+    }
+
+    let cases = benchmark_cases(arguments, config).chain_err(|| "parsing benchmark matrix failed")?;
+    let mut results: Vec<BenchmarkResult> = Vec::new();
+
+    for case in cases {
+        let mandelbrot = Mandelbrot::new(case.region, width, height, max_iterations);
+        let band_height = case.band_height.unwrap_or(64).to_string();
+        let engine = parsed_engine(&case.engine_type, case.in_parallel, &band_height).chain_err(|| "parsing engine type failed")?;
+
+        for _ in 0..warmup {
+            mandelbrot.calculate(&*engine);
+        }
+
+        let mut timings: Vec<f64> = (0..repetitions)
+            .map(|_| {
+                let start = Instant::now();
+                mandelbrot.calculate(&*engine);
+                start.elapsed().as_secs_f64() * 1000.0
+            })
+            .collect();
+        timings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let median_ms = median(&timings);
+        let baseline_ms = results.iter()
+            .find(|result| result.case.region_name == case.region_name)
+            .map_or(median_ms, |result| result.median_ms);
+
+        info!(event = "bench_case", region = case.region_name.as_str(), engine = case.engine_type.as_str(), median_ms = median_ms;
+              "benchmarked {} on {}", case.engine_type, case.region_name);
+
+        results.push(BenchmarkResult {
+            min_ms: timings[0],
+            max_ms: timings[timings.len() - 1],
+            speedup: baseline_ms / median_ms,
+            median_ms,
+            case,
+        });
+    }
+
+    print_table(&results);
+
+    if let Some(csv_filename) = arguments.value_of("csv") {
+        write_csv(&results, csv_filename).chain_err(|| "writing CSV failed")?;
+    }
+
+    Ok(())
+}
+
+
+fn benchmark_cases(arguments: &ArgMatches, config: &Config) -> Result<Vec<BenchmarkCase>> {
+    let engine_types = list_values(arguments.value_of("engines").unwrap());
+    let band_heights = list_values(arguments.value_of("band-heights").unwrap())
+        .iter()
+        .map(|band_height| band_height.parse::<u32>().chain_err(|| "invalid band height"))
+        .collect::<Result<Vec<u32>>>()?;
+
+    let mut modes: Vec<bool> = Vec::new();
+    for mode in list_values(arguments.value_of("modes").unwrap()) {
+        match mode {
+            "serial" => modes.push(false),
+            "parallel" => modes.push(true),
+            _ => bail!("unsupported execution mode"),
+        }
+    }
+
+    let mut cases: Vec<BenchmarkCase> = Vec::new();
+
+    for region_name in list_values(arguments.value_of("regions").unwrap()) {
+        let region = if region_name.contains('@') {
+            parsed_center_and_radius(region_name)?
+        } else {
+            parsed_region(config, Some(region_name), None)?
+        };
+
+        for engine_type in &engine_types {
+            for &in_parallel in &modes {
+                let case_band_heights: Vec<Option<u32>> = if *engine_type == "Recursive" && in_parallel {
+                    band_heights.iter().map(|&band_height| Some(band_height)).collect()
+                } else {
+                    vec![None]
+                };
+
+                for band_height in case_band_heights {
+                    cases.push(BenchmarkCase {
+                        region_name: region_name.to_string(),
+                        region,
+                        engine_type: engine_type.to_string(),
+                        in_parallel,
+                        band_height,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(cases)
+}
+
+
+fn list_values(values: &str) -> Vec<&str> {
+    values
+        .split(',')
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+
+fn median(sorted_timings: &[f64]) -> f64 {
+    let middle = sorted_timings.len() / 2;
+
+    if sorted_timings.len().is_multiple_of(2) {
+        (sorted_timings[middle - 1] + sorted_timings[middle]) / 2.0
+    } else {
+        sorted_timings[middle]
+    }
+}
+
+
+fn band_height_label(case: &BenchmarkCase) -> String {
+    case.band_height.map_or("-".to_string(), |band_height| band_height.to_string())
+}
+
+
+fn mode_label(case: &BenchmarkCase) -> &'static str {
+    if case.in_parallel { "parallel" } else { "serial" }
+}
+
+
+fn print_table(results: &[BenchmarkResult]) {
+    let region_width = results.iter()
+        .map(|result| result.case.region_name.len())
+        .max()
+        .unwrap_or(0)
+        .max("region".len());
+
+    println!("{:<rw$}  {:<9}  {:<8}  {:>4}  {:>11}  {:>11}  {:>11}  {:>7}",
+             "region", "engine", "mode", "band", "median [ms]", "min [ms]", "max [ms]", "speedup", rw = region_width);
+
+    for result in results {
+        println!("{:<rw$}  {:<9}  {:<8}  {:>4}  {:>11.2}  {:>11.2}  {:>11.2}  {:>6.2}x",
+                 result.case.region_name,
+                 result.case.engine_type,
+                 mode_label(&result.case),
+                 band_height_label(&result.case),
+                 result.median_ms,
+                 result.min_ms,
+                 result.max_ms,
+                 result.speedup,
+                 rw = region_width);
+    }
+}
+
+
+fn write_csv(results: &[BenchmarkResult], csv_filename: &str) -> Result<()> {
+    let mut output = File::create(csv_filename)?;
+
+    writeln!(output, "region,engine,mode,band_height,median_ms,min_ms,max_ms,speedup")?;
+
+    for result in results {
+        writeln!(output, "{},{},{},{},{:.3},{:.3},{:.3},{:.3}",
+                 result.case.region_name,
+                 result.case.engine_type,
+                 mode_label(&result.case),
+                 result.case.band_height.map_or(String::new(), |band_height| band_height.to_string()),
+                 result.median_ms,
+                 result.min_ms,
+                 result.max_ms,
+                 result.speedup)?;
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_of_odd_number_of_timings_returns_middle() {
+        // act
+        let result = median(&[1.0, 2.0, 10.0]);
+
+        // assert
+        assert_eq!(result, 2.0);
+    }
+
+    #[test]
+    fn median_of_even_number_of_timings_returns_mean_of_middles() {
+        // act
+        let result = median(&[1.0, 2.0, 4.0, 10.0]);
+
+        // assert
+        assert_eq!(result, 3.0);
+    }
+
+    #[test]
+    fn list_values_skips_empty_entries() {
+        // act
+        let values = list_values("Default, Recursive,,");
+
+        // assert
+        assert_eq!(values, vec!("Default", "Recursive"));
+    }
+}
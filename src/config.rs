@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use clap::{App, AppSettings, ArgMatches, SubCommand};
+use num::complex::Complex64;
+use toml;
+
+use errors::*;
+use fractal::prelude::*;
+use render_parameters::PALETTE;
+
+
+pub const PROJECT_CONFIG_FILENAME: &str = "fractal.toml";
+
+const DEFAULT_ENGINE: &str = "Default";
+const DEFAULT_RESOLUTION: &str = "1024x768";
+const DEFAULT_MAX_ITERATIONS: u8 = 255;
+
+
+// Defaults of the render command and named locations, read from the user config and overridden by the project config
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub engine: Option<String>,
+    pub resolution: Option<String>,
+    pub palette: Option<String>,
+    pub max_iterations: Option<u8>,
+    #[serde(default)]
+    pub presets: BTreeMap<String, Preset>,
+}
+
+
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    pub center: (f64, f64),
+    pub radius: f64,
+    #[serde(default)]
+    pub description: String,
+    // The config file defining the preset
+    #[serde(skip)]
+    pub source: PathBuf,
+}
+
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("presets")
+        .about("Manages the named locations usable with -t (config files: $XDG_CONFIG_HOME/fractal/config.toml, ./fractal.toml)")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("list")
+            .about("Lists the built-in locations and the presets of the config files"))
+}
+
+
+pub fn run(arguments: &ArgMatches, config: &Config) -> Result<()> {
+    match arguments.subcommand_name() {
+        Some("list") => list(config),
+        _ => bail!("unsupported presets command"),
+    }
+}
+
+
+fn list(config: &Config) -> Result<()> {
+    println!("{:<20}  {:<42}  {:>10}  {:<12}  description", "name", "center", "radius", "source");
+
+    for &region_type in RegionType::all() {
+        let region = Region::new_for_type(region_type);
+        let center = Complex64::new(region.min_re() + region.width() / 2.0, region.min_im() + region.height() / 2.0);
+
+        println!("{:<20}  {:<42}  {:>10.3e}  {:<12}  {}", region_type.name(), format!("{}/{}", center.re, center.im),
+                 region.width().max(region.height()) / 2.0, "built-in", region_type.description());
+    }
+
+    for (name, preset) in &config.presets {
+        println!("{:<20}  {:<42}  {:>10.3e}  {:<12}  {}", name, format!("{}/{}", preset.center.0, preset.center.1),
+                 preset.radius, preset.source.display(), preset.description);
+    }
+
+    Ok(())
+}
+
+
+impl Config {
+    pub fn load() -> Result<Config> {
+        let mut config = Config::default();
+
+        for path in config_paths() {
+            if let Some(file_config) = Config::read(&path).chain_err(|| format!("reading config file {} failed", path.display()))? {
+                config.merge(file_config);
+            }
+        }
+
+        Ok(config)
+    }
+
+
+    fn read(path: &Path) -> Result<Option<Config>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut config: Config = toml::from_str(&text)?;
+
+        if config.palette.as_ref().is_some_and(|palette| palette != PALETTE) {
+            bail!("unsupported palette");
+        }
+
+        if config.presets.values().any(|preset| preset.radius <= 0.0) {
+            bail!("preset radii have to be positive");
+        }
+
+        for preset in config.presets.values_mut() {
+            preset.source = path.to_path_buf();
+        }
+
+        Ok(Some(config))
+    }
+
+
+    fn merge(&mut self, other: Config) {
+        if other.engine.is_some() {
+            self.engine = other.engine;
+        }
+
+        if other.resolution.is_some() {
+            self.resolution = other.resolution;
+        }
+
+        if other.palette.is_some() {
+            self.palette = other.palette;
+        }
+
+        if other.max_iterations.is_some() {
+            self.max_iterations = other.max_iterations;
+        }
+
+        self.presets.extend(other.presets);
+    }
+
+
+    pub fn engine(&self) -> &str {
+        self.engine.as_ref().map_or(DEFAULT_ENGINE, |engine| engine.as_str())
+    }
+
+
+    pub fn resolution(&self) -> &str {
+        self.resolution.as_ref().map_or(DEFAULT_RESOLUTION, |resolution| resolution.as_str())
+    }
+
+
+    pub fn max_iterations(&self) -> u8 {
+        self.max_iterations.unwrap_or(DEFAULT_MAX_ITERATIONS)
+    }
+
+
+    // Presets take precedence over the built-in region types of the same name
+    pub fn region(&self, name: &str) -> Option<Region> {
+        match self.presets.get(name) {
+            Some(preset) => Some(Region::new_for_center(Complex64::new(preset.center.0, preset.center.1), preset.radius)),
+            None => RegionType::for_name(name).map(Region::new_for_type),
+        }
+    }
+}
+
+
+// The user config ($XDG_CONFIG_HOME/fractal/config.toml or ~/.config/fractal/config.toml), then the project config
+fn config_paths() -> Vec<PathBuf> {
+    let user_config = match (env::var_os("XDG_CONFIG_HOME"), env::var_os("HOME")) {
+        (Some(config_home), _) => Some(PathBuf::from(config_home).join("fractal").join("config.toml")),
+        (None, Some(home)) => Some(PathBuf::from(home).join(".config").join("fractal").join("config.toml")),
+        (None, None) => None,
+    };
+
+    user_config.into_iter().chain(Some(PathBuf::from(PROJECT_CONFIG_FILENAME))).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(text: &str) -> Config {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn merge_lets_later_config_override_earlier_one() {
+        // arrange
+        let mut config = parsed(r#"
+            engine = "Recursive"
+            resolution = "640x480"
+
+            [presets.Valley]
+            center = [-0.1, 0.6]
+            radius = 0.1
+        "#);
+        let project_config = parsed(r#"
+            resolution = "1920x1080"
+
+            [presets.Valley]
+            center = [-0.2, 0.7]
+            radius = 0.01
+            description = "closer"
+        "#);
+
+        // act
+        config.merge(project_config);
+
+        // assert
+        assert_eq!(config.engine(), "Recursive");
+        assert_eq!(config.resolution(), "1920x1080");
+        assert_eq!(config.max_iterations(), DEFAULT_MAX_ITERATIONS);
+        assert_eq!(config.presets["Valley"].radius, 0.01);
+        assert_eq!(config.presets["Valley"].description, "closer");
+    }
+
+    #[test]
+    fn region_resolves_presets_before_built_in_region_types() {
+        // arrange
+        let config = parsed(r#"
+            [presets.SeaHorseValley]
+            center = [0.0, 0.0]
+            radius = 1.0
+        "#);
+
+        // act & assert
+        assert_eq!(config.region("SeaHorseValley"), Some(Region::new(-1.0, 1.0, -1.0, 1.0)));
+        assert_eq!(config.region("ElephantValley"), Some(Region::new_for_type(RegionType::ElephantValley)));
+        assert_eq!(config.region("Atlantis"), None);
+    }
+
+    #[test]
+    fn unknown_settings_are_rejected() {
+        // act
+        let config = toml::from_str::<Config>("enigne = \"Recursive\"");
+
+        // assert
+        assert!(config.is_err());
+    }
+}
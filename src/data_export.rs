@@ -0,0 +1,271 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde_json;
+
+use errors::*;
+use fractal::prelude::*;
+use fractal::pixel::Pixel;
+use render_parameters::FORMULA;
+
+
+const RAW_MAGIC: &[u8] = b"FRACRAW1";
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+const NPY_HEADER_ALIGNMENT: usize = 64;
+const CSV_PIXEL_LIMIT: u64 = 1_000_000;
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum DataFormat {
+    Npy,
+    Raw,
+    Csv,
+}
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum DataValues {
+    Counts,
+    Smooth,
+}
+
+
+pub struct DataOutput {
+    pub filename: String,
+    pub format: DataFormat,
+    pub values: DataValues,
+}
+
+
+#[derive(Debug, Serialize)]
+struct DataHeader {
+    values: &'static str,
+    dtype: &'static str,
+    byte_order: &'static str,
+    width: u32,
+    height: u32,
+    max_iterations: u8,
+    formula: &'static str,
+    region: RegionHeader,
+    pixel_to_complex: PixelMapping,
+}
+
+
+#[derive(Debug, Serialize)]
+struct RegionHeader {
+    min_re: f64,
+    min_im: f64,
+    width: f64,
+    height: f64,
+}
+
+
+#[derive(Debug, Serialize)]
+struct PixelMapping {
+    re: &'static str,
+    im: &'static str,
+    re_step: f64,
+    im_step: f64,
+}
+
+
+impl DataOutput {
+    pub fn new(filename: &str, values: &str) -> Result<DataOutput> {
+        let extension = Path::new(filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+
+        let format = match extension.as_deref() {
+            Some("npy") => DataFormat::Npy,
+            Some("raw") | Some("bin") => DataFormat::Raw,
+            Some("csv") => DataFormat::Csv,
+            _ => bail!("data format cannot be derived from the file name extension (values: npy, raw, bin, csv)")
+        };
+
+        let values = match values {
+            "counts" => DataValues::Counts,
+            "smooth" => DataValues::Smooth,
+            _ => bail!("unsupported data values")
+        };
+
+        Ok(DataOutput { filename: filename.to_string(), format, values })
+    }
+
+
+    pub fn needs_smooth_values(&self) -> bool {
+        self.values == DataValues::Smooth
+    }
+}
+
+
+pub fn write_data(data_output: &DataOutput, mandelbrot: &Mandelbrot, pixels: &[u8], smooth_values: Option<&[f32]>) -> Result<()> {
+    let header = data_header(data_output.values, mandelbrot);
+    let mut output = BufWriter::new(File::create(&data_output.filename)?);
+
+    match data_output.format {
+        DataFormat::Npy => {
+            output.write_all(&npy_header(header.dtype, mandelbrot.width, mandelbrot.height))?;
+            write_values(&mut output, data_output.values, pixels, smooth_values)?;
+
+            // The NumPy format cannot carry additional metadata, so it is written beside the data
+            let metadata = File::create(format!("{}.json", data_output.filename))?;
+            serde_json::to_writer_pretty(metadata, &header)?;
+        },
+        DataFormat::Raw => {
+            let json_header = serde_json::to_vec(&header)?;
+
+            output.write_all(RAW_MAGIC)?;
+            output.write_all(&(json_header.len() as u32).to_le_bytes())?;
+            output.write_all(&json_header)?;
+            write_values(&mut output, data_output.values, pixels, smooth_values)?;
+        },
+        DataFormat::Csv => {
+            if (mandelbrot.width as u64) * (mandelbrot.height as u64) > CSV_PIXEL_LIMIT {
+                warn!("CSV export of {}x{} pixels will be large, consider npy or raw instead", mandelbrot.width, mandelbrot.height);
+            }
+
+            writeln!(output, "# {}", serde_json::to_string(&header)?)?;
+            write_csv_rows(&mut output, data_output.values, mandelbrot, pixels, smooth_values)?;
+        },
+    }
+
+    output.flush()?;
+
+    Ok(())
+}
+
+
+fn data_header(values: DataValues, mandelbrot: &Mandelbrot) -> DataHeader {
+    let (values, dtype) = match values {
+        DataValues::Counts => ("escape_counts", "u32"),
+        DataValues::Smooth => ("smooth_escape_times", "f32"),
+    };
+
+    DataHeader {
+        values,
+        dtype,
+        byte_order: "little",
+        width: mandelbrot.width,
+        height: mandelbrot.height,
+        max_iterations: mandelbrot.max_iterations,
+        formula: FORMULA,
+        region: RegionHeader {
+            min_re: mandelbrot.region.min_re(),
+            min_im: mandelbrot.region.min_im(),
+            width: mandelbrot.region.width(),
+            height: mandelbrot.region.height(),
+        },
+        pixel_to_complex: PixelMapping {
+            re: "region.min_re + x * region.width / width",
+            im: "region.min_im + y * region.height / height",
+            re_step: mandelbrot.region.width() / (mandelbrot.width as f64),
+            im_step: mandelbrot.region.height() / (mandelbrot.height as f64),
+        },
+    }
+}
+
+
+fn npy_header(dtype: &str, width: u32, height: u32) -> Vec<u8> {
+    let descr = if dtype == "u32" { "<u4" } else { "<f4" };
+    let mut dictionary = format!("{{'descr': '{}', 'fortran_order': False, 'shape': ({}, {}), }}", descr, height, width);
+
+    // magic, version and header length precede the dictionary, which is padded and terminated by a newline
+    let unpadded_length = NPY_MAGIC.len() + 2 + 2 + dictionary.len() + 1;
+    let padding = (NPY_HEADER_ALIGNMENT - unpadded_length % NPY_HEADER_ALIGNMENT) % NPY_HEADER_ALIGNMENT;
+    dictionary.push_str(&" ".repeat(padding));
+    dictionary.push('\n');
+
+    let mut header: Vec<u8> = Vec::new();
+    header.extend_from_slice(NPY_MAGIC);
+    header.extend_from_slice(&[1, 0]);
+    header.extend_from_slice(&(dictionary.len() as u16).to_le_bytes());
+    header.extend_from_slice(dictionary.as_bytes());
+
+    header
+}
+
+
+fn write_values<W: Write>(output: &mut W, values: DataValues, pixels: &[u8], smooth_values: Option<&[f32]>) -> Result<()> {
+    match values {
+        DataValues::Counts => {
+            for &pixel in pixels {
+                output.write_all(&(pixel as u32).to_le_bytes())?;
+            }
+        },
+        DataValues::Smooth => {
+            for value in smooth_values.ok_or("smooth iteration values are required")? {
+                output.write_all(&value.to_le_bytes())?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+
+fn write_csv_rows<W: Write>(output: &mut W, values: DataValues, mandelbrot: &Mandelbrot, pixels: &[u8], smooth_values: Option<&[f32]>) -> Result<()> {
+    writeln!(output, "x,y,re,im,value")?;
+
+    let smooth_values = match values {
+        DataValues::Counts => None,
+        DataValues::Smooth => Some(smooth_values.ok_or("smooth iteration values are required")?),
+    };
+
+    for (index, &pixel) in pixels.iter().enumerate() {
+        let x = (index % mandelbrot.width as usize) as u32;
+        let y = (index / mandelbrot.width as usize) as u32;
+        let point = mandelbrot.point_for_pixel(&Pixel { x, y });
+
+        match smooth_values {
+            Some(smooth_values) => writeln!(output, "{},{},{},{},{}", x, y, point.re, point.im, smooth_values[index])?,
+            None => writeln!(output, "{},{},{},{},{}", x, y, point.re, point.im, pixel)?,
+        }
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn npy_header_is_aligned_and_describes_data() {
+        // act
+        let header = npy_header("u32", 640, 480);
+
+        // assert
+        assert_eq!(header.len() % NPY_HEADER_ALIGNMENT, 0);
+        assert!(header.starts_with(NPY_MAGIC));
+        assert_eq!(header[header.len() - 1], b'\n');
+
+        let dictionary = String::from_utf8(header[10..].to_vec()).unwrap();
+        assert!(dictionary.starts_with("{'descr': '<u4', 'fortran_order': False, 'shape': (480, 640), }"));
+    }
+
+    #[test]
+    fn new_derives_format_from_extension() {
+        // act
+        let data_output = DataOutput::new("field.NPY", "smooth").unwrap();
+
+        // assert
+        assert_eq!(data_output.format, DataFormat::Npy);
+        assert_eq!(data_output.values, DataValues::Smooth);
+        assert!(data_output.needs_smooth_values());
+    }
+
+    #[test]
+    fn write_values_writes_little_endian_counts() {
+        // arrange
+        let mut output: Vec<u8> = Vec::new();
+
+        // act
+        write_values(&mut output, DataValues::Counts, &[1, 255], None).unwrap();
+
+        // assert
+        assert_eq!(output, vec!(1, 0, 0, 0, 255, 0, 0, 0));
+    }
+}
@@ -0,0 +1,574 @@
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use crossterm::{cursor, execute, queue, terminal};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use log::{self, LevelFilter};
+use num::complex::Complex64;
+
+use errors::*;
+use fractal::coloring;
+use fractal::prelude::*;
+use animate::{region_center, region_radius};
+use config::Config;
+use output_format::OutputFormat;
+use render_parameters::RenderParameters;
+use text_art::{half_block_lines, AnsiColors};
+use super::{create_mandelbrot_file, elapsed_milliseconds, parsed_resolution, parsed_max_iterations, parsed_region};
+
+
+// Pan step as part of the radius
+const PAN_STEP: f64 = 0.25;
+const ZOOM_FACTOR: f64 = 1.5;
+const ITERATION_STEP: u8 = 16;
+// Cell size in pixels if the terminal does not report its size in pixels
+const DEFAULT_CELL_WIDTH: f64 = 8.0;
+const DEFAULT_CELL_HEIGHT: f64 = 16.0;
+const SIXEL_COLORS: u16 = 64;
+const KITTY_CHUNK_SIZE: usize = 4096;
+const HELP: &str = "arrows/hjkl pan  +/- zoom  [/] iterations  e engine  u undo  s export  q quit";
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Graphics {
+    // Unicode upper half blocks in truecolor, two pixels per character
+    Blocks,
+    Kitty,
+    Sixel,
+}
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct View {
+    center: Complex64,
+    radius: f64,
+    max_iterations: u8,
+    engine_type: EngineType,
+}
+
+
+struct Explorer {
+    view: View,
+    history: Vec<View>,
+    graphics: Graphics,
+    export_width: u32,
+    export_height: u32,
+    export_prefix: String,
+    exports: Vec<String>,
+    message: String,
+}
+
+
+// Raw mode on the alternate screen with logging turned off while exploring, restored when dropped
+struct TerminalSession {
+    graphics: Graphics,
+    max_log_level: LevelFilter,
+}
+
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("explore")
+        .about("Explores the Mandelbrot set interactively in the terminal")
+        .arg(Arg::with_name("type")
+            .short("t")
+            .long("type")
+            .help("Sets the initial view by a region type or preset (see: fractal presets list)")
+            .takes_value(true)
+            .value_name("TYPE")
+            .default_value("Default"))
+        .arg(Arg::with_name("center-and-radius")
+            .short("c")
+            .long("center-and-radius")
+            .help("Specifies the center and radius of the initial view (format: <real>/<imag>@radius)")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("CENTER-AND-RADIUS"))
+        .arg(Arg::with_name("max-iterations")
+            .short("i")
+            .long("max-iterations")
+            .help("Sets the initial max iterations (default: from the config)")
+            .takes_value(true)
+            .value_name("MAX-ITERATIONS"))
+        .arg(Arg::with_name("engine")
+            .short("e")
+            .long("engine")
+            .help("Choose the initial engine (values: Default, Recursive)")
+            .takes_value(true)
+            .value_name("ENGINE")
+            .default_value("Recursive"))
+        .arg(Arg::with_name("resolution")
+            .short("r")
+            .long("resolution")
+            .help("Specifies the resolution of exported images, the view keeps its aspect ratio (format: <width>x<height>, default: from the config)")
+            .takes_value(true)
+            .value_name("RESOLUTION"))
+        .arg(Arg::with_name("graphics")
+            .long("graphics")
+            .help("Specifies how the view is drawn (values: auto, blocks, kitty, sixel)")
+            .takes_value(true)
+            .value_name("GRAPHICS")
+            .default_value("auto"))
+        .arg(Arg::with_name("output-prefix")
+            .short("o")
+            .long("output-prefix")
+            .help("Exported images are written to <OUTPUT-PREFIX>-<n>.png")
+            .takes_value(true)
+            .value_name("OUTPUT-PREFIX")
+            .default_value("explore"))
+}
+
+
+pub fn run(arguments: &ArgMatches, config: &Config) -> Result<()> {
+    let region = parsed_region(config, arguments.value_of("type"), arguments.value_of("center-and-radius")).chain_err(|| "parsing region failed")?;
+    let max_iterations = match arguments.value_of("max-iterations") {
+        Some(max_iterations) => parsed_max_iterations(max_iterations).chain_err(|| "parsing max iterations failed")?,
+        None => config.max_iterations(),
+    };
+    let engine_type = EngineType::for_name(arguments.value_of("engine").unwrap()).ok_or("unsupported engine type")?;
+    let (export_width, export_height) = parsed_resolution(arguments.value_of("resolution").unwrap_or(config.resolution()))
+        .chain_err(|| "parsing resolution failed")?;
+    let graphics = Graphics::for_name(arguments.value_of("graphics").unwrap())?;
+
+    if max_iterations == 0 || export_width == 0 || export_height == 0 {
+        bail!("max iterations and resolution have to be positive");
+    }
+
+    let mut explorer = Explorer {
+        view: View { center: region_center(&region), radius: region_radius(&region), max_iterations, engine_type },
+        history: Vec::new(),
+        graphics,
+        export_width,
+        export_height,
+        export_prefix: arguments.value_of("output-prefix").unwrap().to_string(),
+        exports: Vec::new(),
+        message: HELP.to_string(),
+    };
+
+    {
+        let _session = TerminalSession::start(graphics)?;
+
+        loop {
+            explorer.draw()?;
+
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => {
+                    if !explorer.handle_key(key) {
+                        break;
+                    }
+                },
+                Event::Resize(_, _) => {},
+                _ => continue,
+            }
+        }
+    }
+
+    for export in &explorer.exports {
+        println!("{}", export);
+    }
+
+    Ok(())
+}
+
+
+impl Graphics {
+    pub fn for_name(name: &str) -> Result<Graphics> {
+        match name {
+            "auto" => Ok(Graphics::detected()),
+            "blocks" => Ok(Graphics::Blocks),
+            "kitty" => Ok(Graphics::Kitty),
+            "sixel" => Ok(Graphics::Sixel),
+            _ => bail!("unsupported graphics"),
+        }
+    }
+
+
+    // Terminals are recognized by their environment, there is no portable way to query the graphics protocols
+    fn detected() -> Graphics {
+        let term = env::var("TERM").unwrap_or_default();
+        let term_program = env::var("TERM_PROGRAM").unwrap_or_default();
+
+        if env::var_os("KITTY_WINDOW_ID").is_some() || term.contains("kitty") || term.contains("ghostty") || term_program == "WezTerm" {
+            Graphics::Kitty
+        } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+            Graphics::Sixel
+        } else {
+            Graphics::Blocks
+        }
+    }
+}
+
+
+impl View {
+    // Moves the center by parts of the radius in screen directions (rows go down with growing imaginary part)
+    fn panned(self, right: f64, down: f64) -> View {
+        View { center: self.center + Complex64::new(right, down) * self.radius, ..self }
+    }
+
+
+    fn zoomed(self, factor: f64) -> View {
+        View { radius: self.radius / factor, ..self }
+    }
+
+
+    fn with_max_iterations(self, max_iterations: u8) -> View {
+        View { max_iterations: max_iterations.max(1), ..self }
+    }
+
+
+    fn with_next_engine(self) -> View {
+        let engine_types = EngineType::all();
+        let index = engine_types.iter().position(|&engine_type| engine_type == self.engine_type).unwrap_or(0);
+
+        View { engine_type: engine_types[(index + 1) % engine_types.len()], ..self }
+    }
+
+
+    fn region(&self) -> Region {
+        Region::new_for_center(self.center, self.radius)
+    }
+
+
+    // The argument of -c for this view
+    fn center_and_radius(&self) -> String {
+        format!("{}/{}@{}", self.center.re, self.center.im, self.radius)
+    }
+
+
+    fn renderer(&self, width: u32, height: u32) -> Renderer {
+        Renderer::new()
+            .region(self.region())
+            .size(width, height)
+            .max_iterations(self.max_iterations)
+            .engine_type(self.engine_type)
+            .parallel(true)
+    }
+}
+
+
+impl Explorer {
+    // Returns false to quit
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let view = self.view;
+
+        let next_view = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Left | KeyCode::Char('h') => view.panned(-PAN_STEP, 0.0),
+            KeyCode::Right | KeyCode::Char('l') => view.panned(PAN_STEP, 0.0),
+            KeyCode::Up | KeyCode::Char('k') => view.panned(0.0, -PAN_STEP),
+            KeyCode::Down | KeyCode::Char('j') => view.panned(0.0, PAN_STEP),
+            KeyCode::Char('+') | KeyCode::Char('=') => view.zoomed(ZOOM_FACTOR),
+            KeyCode::Char('-') | KeyCode::Char('_') => view.zoomed(1.0 / ZOOM_FACTOR),
+            KeyCode::Char(']') => view.with_max_iterations(view.max_iterations.saturating_add(ITERATION_STEP)),
+            KeyCode::Char('[') => view.with_max_iterations(view.max_iterations.saturating_sub(ITERATION_STEP)),
+            KeyCode::Char('e') => view.with_next_engine(),
+            KeyCode::Char('u') | KeyCode::Backspace => {
+                match self.history.pop() {
+                    Some(previous_view) => self.view = previous_view,
+                    None => self.message = "nothing to undo".to_string(),
+                }
+
+                return true;
+            },
+            KeyCode::Char('s') => {
+                self.message = match self.export() {
+                    Ok(export) => {
+                        self.exports.push(export.clone());
+                        format!("exported: {}", export)
+                    },
+                    Err(e) => format!("export failed: {}", e),
+                };
+
+                return true;
+            },
+            _ => {
+                self.message = HELP.to_string();
+                return true;
+            },
+        };
+
+        if next_view != view {
+            self.history.push(view);
+            self.view = next_view;
+        }
+
+        true
+    }
+
+
+    // Renders the view at the export resolution into the first unused <prefix>-<n>.png and returns the equivalent command
+    fn export(&self) -> Result<String> {
+        let filename = (1..)
+            .map(|n| format!("{}-{}.png", self.export_prefix, n))
+            .find(|filename| !Path::new(filename).exists())
+            .unwrap();
+
+        let parameters = RenderParameters {
+            region: self.view.region(),
+            width: self.export_width,
+            height: self.export_height,
+            max_iterations: self.view.max_iterations,
+            engine_type: self.view.engine_type.name().to_string(),
+            in_parallel: true,
+            band_height: 64,
+        };
+
+        create_mandelbrot_file(&parameters, &filename, OutputFormat::for_name("png")?, None, None, None)?;
+
+        Ok(format!("fractal -c {} -r {}x{} -i {} -e {} -p -o {}",
+                   self.view.center_and_radius(), self.export_width, self.export_height, self.view.max_iterations,
+                   self.view.engine_type.name(), filename))
+    }
+
+
+    // Draws the view as the export would look like, scaled to fit above the status line
+    fn draw(&self) -> Result<()> {
+        // Some terminals only report their size in cells
+        let size = terminal::window_size()
+            .or_else(|_| terminal::size().map(|(columns, rows)| terminal::WindowSize { rows, columns, width: 0, height: 0 }))?;
+        let rows = size.rows.saturating_sub(1).max(1);
+        let columns = size.columns.max(1);
+
+        let (cell_width, cell_height) = if size.width > 0 && size.height > 0 {
+            (size.width as f64 / size.columns as f64, size.height as f64 / size.rows as f64)
+        } else {
+            (DEFAULT_CELL_WIDTH, DEFAULT_CELL_HEIGHT)
+        };
+
+        let (area_width, area_height) = match self.graphics {
+            Graphics::Blocks => (columns as f64, rows as f64 * 2.0),
+            Graphics::Kitty | Graphics::Sixel => (columns as f64 * cell_width, rows as f64 * cell_height),
+        };
+
+        let (width, height) = fitted_size(area_width, area_height, self.export_width, self.export_height);
+
+        let render_start = Instant::now();
+        let image = self.view.renderer(width, height).render();
+        let elapsed_ms = elapsed_milliseconds(render_start);
+
+        let image_columns = match self.graphics {
+            Graphics::Blocks => width as f64,
+            Graphics::Kitty | Graphics::Sixel => (width as f64 / cell_width).ceil(),
+        };
+        let left = ((columns as f64 - image_columns) / 2.0).max(0.0) as u16;
+
+        let mut output: Vec<u8> = Vec::new();
+
+        if self.graphics == Graphics::Kitty {
+            output.extend_from_slice(KITTY_DELETE_IMAGES.as_bytes());
+        }
+
+        queue!(output, terminal::Clear(terminal::ClearType::All))?;
+
+        match self.graphics {
+            Graphics::Blocks => {
+                for (row, line) in half_block_lines(&image.pixels, width as usize, height as usize, AnsiColors::TrueColor).iter().enumerate() {
+                    queue!(output, cursor::MoveTo(left, row as u16))?;
+                    output.extend_from_slice(line.as_bytes());
+                }
+            },
+            Graphics::Kitty => {
+                queue!(output, cursor::MoveTo(left, 0))?;
+                output.extend_from_slice(kitty_image(&image.pixels, width, height).as_bytes());
+            },
+            Graphics::Sixel => {
+                queue!(output, cursor::MoveTo(left, 0))?;
+                output.extend_from_slice(sixel_image(&image.pixels, width as usize, height as usize).as_bytes());
+            },
+        }
+
+        let status = format!(" -c {}  -i {}  -e {}  {:.0} ms  {}", self.view.center_and_radius(), self.view.max_iterations,
+                             self.view.engine_type.name(), elapsed_ms, self.message);
+
+        queue!(output, cursor::MoveTo(0, rows))?;
+        output.extend(status.chars().take(columns as usize).collect::<String>().bytes());
+
+        let mut stdout = io::stdout();
+        stdout.write_all(&output)?;
+        stdout.flush()?;
+
+        Ok(())
+    }
+}
+
+
+impl TerminalSession {
+    fn start(graphics: Graphics) -> Result<TerminalSession> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+
+        let max_log_level = log::max_level();
+        log::set_max_level(LevelFilter::Off);
+
+        Ok(TerminalSession { graphics, max_log_level })
+    }
+}
+
+
+impl Drop for TerminalSession {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+
+        if self.graphics == Graphics::Kitty {
+            let _ = stdout.write_all(KITTY_DELETE_IMAGES.as_bytes());
+        }
+
+        let _ = execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+
+        log::set_max_level(self.max_log_level);
+    }
+}
+
+
+const KITTY_DELETE_IMAGES: &str = "\x1b_Ga=d,q=2\x1b\\";
+
+
+// The largest size of the aspect ratio of the export resolution within the area
+fn fitted_size(area_width: f64, area_height: f64, export_width: u32, export_height: u32) -> (u32, u32) {
+    let scale = (area_width / export_width as f64).min(area_height / export_height as f64);
+
+    (((export_width as f64 * scale) as u32).max(1), ((export_height as f64 * scale) as u32).max(1))
+}
+
+
+// Transmits the gray levels as RGB in chunks and displays them at the cursor (kitty graphics protocol)
+fn kitty_image(pixels: &[u8], width: u32, height: u32) -> String {
+    let rgb: Vec<u8> = pixels.iter().flat_map(|&level| vec![level, level, level]).collect();
+    let data = base64(&rgb);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+    let mut image = String::new();
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+
+        if i == 0 {
+            write!(image, "\x1b_Ga=T,f=24,s={},v={},q=2,m={};", width, height, more).unwrap();
+        } else {
+            write!(image, "\x1b_Gm={};", more).unwrap();
+        }
+
+        image.push_str(::std::str::from_utf8(chunk).unwrap());
+        image.push_str("\x1b\\");
+    }
+
+    image
+}
+
+
+// Dithered gray levels as sixels: every band of six rows is drawn once per color used in it
+fn sixel_image(pixels: &[u8], width: usize, height: usize) -> String {
+    let indices = coloring::quantized(pixels, width, SIXEL_COLORS, true);
+    let palette = coloring::gray_palette(SIXEL_COLORS);
+    let mut image = format!("\x1bPq\"1;1;{};{}", width, height);
+
+    for color in 0..SIXEL_COLORS as usize {
+        let percent = (palette[color * 3] as f64 * 100.0 / 255.0).round();
+        write!(image, "#{};2;{};{};{}", color, percent, percent, percent).unwrap();
+    }
+
+    for band_y in (0..height).step_by(6) {
+        let band_rows = (height - band_y).min(6);
+        let mut used_colors: Vec<u8> = indices[band_y * width..(band_y + band_rows) * width].to_vec();
+        used_colors.sort_unstable();
+        used_colors.dedup();
+
+        for color in used_colors {
+            let sixels: Vec<u8> = (0..width)
+                .map(|x| {
+                    let bits = (0..band_rows)
+                        .filter(|row| indices[(band_y + row) * width + x] == color)
+                        .fold(0, |bits, row| bits | (1 << row));
+
+                    63 + bits
+                })
+                .collect();
+
+            write!(image, "#{}", color).unwrap();
+
+            for run in sixels.chunk_by(|a, b| a == b) {
+                if run.len() > 3 {
+                    write!(image, "!{}{}", run.len(), run[0] as char).unwrap();
+                } else {
+                    image.extend(run.iter().map(|&sixel| sixel as char));
+                }
+            }
+
+            image.push('$');
+        }
+
+        image.push('-');
+    }
+
+    image.push_str("\x1b\\");
+    image
+}
+
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    data.chunks(3)
+        .flat_map(|chunk| {
+            let bits = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+
+            (0..4).map(move |i| if i <= chunk.len() { ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char } else { '=' })
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view() -> View {
+        View { center: Complex64::new(-0.5, 0.0), radius: 1.0, max_iterations: 250, engine_type: EngineType::Recursive }
+    }
+
+    #[test]
+    fn view_changes_are_undone_from_history() {
+        // arrange
+        let mut explorer = Explorer {
+            view: view(),
+            history: Vec::new(),
+            graphics: Graphics::Blocks,
+            export_width: 640,
+            export_height: 480,
+            export_prefix: "explore".to_string(),
+            exports: Vec::new(),
+            message: String::new(),
+        };
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        // act
+        explorer.handle_key(key(KeyCode::Right));
+        explorer.handle_key(key(KeyCode::Char('+')));
+        explorer.handle_key(key(KeyCode::Char(']')));
+        explorer.handle_key(key(KeyCode::Char('e')));
+        let changed_view = explorer.view;
+        explorer.handle_key(key(KeyCode::Char('u')));
+        explorer.handle_key(key(KeyCode::Char('u')));
+        explorer.handle_key(key(KeyCode::Char('u')));
+
+        // assert
+        assert_eq!(changed_view.center, Complex64::new(-0.25, 0.0));
+        assert_eq!(changed_view.radius, 1.0 / ZOOM_FACTOR);
+        assert_eq!(changed_view.max_iterations, 255);
+        assert_eq!(changed_view.engine_type, EngineType::Simple);
+        assert_eq!(explorer.view, view().panned(PAN_STEP, 0.0));
+        assert!(!explorer.handle_key(key(KeyCode::Char('q'))));
+    }
+
+    #[test]
+    fn base64_pads_last_group() {
+        // act & assert
+        assert_eq!(base64(b"fractal"), "ZnJhY3RhbA==");
+        assert_eq!(base64(b"fractals"), "ZnJhY3RhbHM=");
+        assert_eq!(base64(b"fractal!!"), "ZnJhY3RhbCEh");
+    }
+}
@@ -0,0 +1,418 @@
+use std::f64::consts::{PI, SQRT_2};
+use std::fs::File;
+use std::io::BufWriter;
+use std::time::Instant;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use num::complex::Complex64;
+use png;
+
+use errors::*;
+use config::Config;
+use fractal::prelude::*;
+use animate::{Easing, FrameParallelism, frame_output, frame_output_arguments, region_center, region_radius, render_frames, zoom_frames};
+use png_file;
+use render_parameters::{KEYWORD_PREFIX, SOFTWARE_KEYWORD, software};
+use super::{elapsed_milliseconds, parsed_resolution, parsed_max_iterations, parsed_region, parsed_engine};
+
+
+const PROJECTION: &str = "exponential-map";
+
+
+// A log-polar strip around a center: the angle goes across, the distance shrinks exponentially going down.
+// Rows are spaced like columns (2 pi / width in log distance), so every zoom frame down to the inner radius
+// can be reprojected from the strip without rendering.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExponentialMap {
+    pub center: Complex64,
+    pub outer_radius: f64,
+    pub inner_radius: f64,
+    pub width: u32,
+    pub height: u32,
+    pub max_iterations: u8,
+}
+
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("expmap")
+        .about("Renders a zoom as a single log-polar exponential map strip and reprojects the strip into zoom frames")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("strip")
+            .about("Renders the exponential map strip of a zoom into the center of a region")
+            .arg(Arg::with_name("type")
+                .short("t")
+                .long("type")
+                .help("Sets the region of the first frame by a region type or preset (see: fractal presets list)")
+                .takes_value(true)
+                .value_name("TYPE")
+                .default_value("Default"))
+            .arg(Arg::with_name("center-and-radius")
+                .short("c")
+                .long("center-and-radius")
+                .conflicts_with("type")
+                .help("Specifies the center and radius of the first frame (format: <real>/<imag>@radius)")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .value_name("CENTER-AND-RADIUS"))
+            .arg(Arg::with_name("target-radius")
+                .long("target-radius")
+                .help("Radius of the last frame")
+                .required(true)
+                .takes_value(true)
+                .value_name("TARGET-RADIUS"))
+            .arg(Arg::with_name("resolution")
+                .short("r")
+                .long("resolution")
+                .help("Resolution of the frames the strip is made for, determines its width and depth (format: <width>x<height>)")
+                .value_name("RESOLUTION")
+                .default_value("640x480"))
+            .arg(Arg::with_name("strip-width")
+                .short("w")
+                .long("strip-width")
+                .help("Overrides the width of the strip (default: the circumference of the frame corners in pixels)")
+                .takes_value(true)
+                .value_name("STRIP-WIDTH"))
+            .arg(Arg::with_name("max-iterations")
+                .short("i")
+                .long("max-iterations")
+                .help("Sets max iterations (default: from the config, or 255)")
+                .takes_value(true)
+                .value_name("MAX-ITERATIONS"))
+            .arg(Arg::with_name("parallel")
+                .short("p")
+                .long("parallel")
+                .help("If specified the calculation is done in parallel"))
+            .arg(Arg::with_name("band-height")
+                .short("b")
+                .long("band-height")
+                .help("Specifies number of rows per band (only sensible in case of parallel execution of recursive engine)")
+                .value_name("BAND-HEIGHT")
+                .default_value("64"))
+            .arg(Arg::with_name("engine")
+                .short("e")
+                .long("engine")
+                .help("Choose engine to to use (values: Default, Recursive, default: from the config, or Default)")
+                .takes_value(true)
+                .value_name("ENGINE"))
+            .arg(Arg::with_name("output-filename")
+                .short("o")
+                .long("output-filename")
+                .help("PNG file for the strip")
+                .required(true)
+                .takes_value(true)
+                .value_name("OUTPUT-FILENAME")))
+        .subcommand(SubCommand::with_name("reproject")
+            .about("Reprojects an exponential map strip into the frames of a zoom")
+            .arg(Arg::with_name("strip")
+                .help("PNG strip created by expmap strip")
+                .required(true)
+                .value_name("STRIP"))
+            .arg(Arg::with_name("frames")
+                .short("n")
+                .long("frames")
+                .help("Number of frames")
+                .takes_value(true)
+                .value_name("FRAMES")
+                .default_value("100"))
+            .arg(Arg::with_name("fps")
+                .long("fps")
+                .help("Frames per second of the Y4M stream, GIF or APNG")
+                .takes_value(true)
+                .value_name("FPS")
+                .default_value("30"))
+            .arg(Arg::with_name("easing")
+                .long("easing")
+                .help("Zoom speed over time (values: smooth, linear)")
+                .takes_value(true)
+                .value_name("EASING")
+                .default_value("smooth"))
+            .arg(Arg::with_name("start-radius")
+                .long("start-radius")
+                .help("Radius of the first frame (default: the largest radius whose corners are covered by the strip)")
+                .takes_value(true)
+                .value_name("START-RADIUS"))
+            .arg(Arg::with_name("target-radius")
+                .long("target-radius")
+                .help("Radius of the last frame (default: the inner radius of the strip is one pixel of the last frame)")
+                .takes_value(true)
+                .value_name("TARGET-RADIUS"))
+            .arg(Arg::with_name("resolution")
+                .short("r")
+                .long("resolution")
+                .help("Specifies the resolution of the frames (format: <width>x<height>)")
+                .value_name("RESOLUTION")
+                .default_value("640x480"))
+            .args(&frame_output_arguments()))
+}
+
+
+pub fn run(arguments: &ArgMatches, config: &Config) -> Result<()> {
+    match arguments.subcommand() {
+        ("strip", Some(strip_arguments)) => run_strip(strip_arguments, config),
+        ("reproject", Some(reproject_arguments)) => run_reproject(reproject_arguments),
+        _ => bail!("unsupported expmap command"),
+    }
+}
+
+
+fn run_strip(arguments: &ArgMatches, config: &Config) -> Result<()> {
+    let region = parsed_region(config, arguments.value_of("type"), arguments.value_of("center-and-radius")).chain_err(|| "parsing region failed")?;
+    let target_radius = arguments.value_of("target-radius").unwrap().parse::<f64>().chain_err(|| "invalid target radius")?;
+    let (width, height) = parsed_resolution(arguments.value_of("resolution").unwrap()).chain_err(|| "parsing resolution failed")?;
+    let max_iterations = match arguments.value_of("max-iterations") {
+        Some(max_iterations) => parsed_max_iterations(max_iterations).chain_err(|| "parsing max iterations failed")?,
+        None => config.max_iterations(),
+    };
+    let engine = parsed_engine(arguments.value_of("engine").unwrap_or(config.engine()), arguments.is_present("parallel"), arguments.value_of("band-height").unwrap())
+        .chain_err(|| "parsing engine type failed")?;
+
+    let frame_size = width.max(height) as f64;
+    let strip_width = match arguments.value_of("strip-width") {
+        Some(strip_width) => strip_width.parse::<u32>().chain_err(|| "invalid strip width")?,
+        None => (PI * SQRT_2 * frame_size).ceil() as u32,
+    };
+
+    if target_radius <= 0.0 || target_radius >= region_radius(&region) || strip_width == 0 {
+        bail!("the target radius has to be positive and smaller than the radius, the strip width positive");
+    }
+
+    // From the corners of the first frame down to a single pixel at the center of the last frame
+    let map = ExponentialMap::new(region_center(&region), region_radius(&region) * SQRT_2, 2.0 * target_radius / frame_size,
+                                  strip_width, max_iterations);
+
+    let render_start = Instant::now();
+    let (pixels, _) = map.mandelbrot().calculate(&*engine);
+
+    info!(event = "strip_finished", width = map.width, height = map.height, elapsed_ms = elapsed_milliseconds(render_start);
+          "strip of {}x{} pixels finished", map.width, map.height);
+
+    let output = BufWriter::new(File::create(arguments.value_of("output-filename").unwrap())?);
+
+    png_file::write(output, map.width, map.height, png::ColorType::Grayscale, png::BitDepth::Eight, &pixels, &map.to_text_chunks())
+}
+
+
+fn run_reproject(arguments: &ArgMatches) -> Result<()> {
+    let strip_filename = arguments.value_of("strip").unwrap();
+    let text_chunks = png_file::read_text_chunks(strip_filename).chain_err(|| "reading strip failed")?;
+    let (strip_width, strip_height, strip) = png_file::read_grayscale(strip_filename).chain_err(|| "reading strip failed")?;
+    let map = ExponentialMap::from_text_chunks(&text_chunks, strip_width, strip_height).chain_err(|| "reading exponential map parameters from strip failed")?;
+
+    let frame_count = arguments.value_of("frames").unwrap().parse::<u32>().chain_err(|| "invalid number of frames")?;
+    let fps = arguments.value_of("fps").unwrap().parse::<u32>().chain_err(|| "invalid fps")?;
+    let easing = match arguments.value_of("easing").unwrap() {
+        "smooth" => Easing::Smooth,
+        "linear" => Easing::Linear,
+        _ => bail!("unsupported easing"),
+    };
+    let (width, height) = parsed_resolution(arguments.value_of("resolution").unwrap()).chain_err(|| "parsing resolution failed")?;
+
+    let start_radius = match arguments.value_of("start-radius") {
+        Some(start_radius) => start_radius.parse::<f64>().chain_err(|| "invalid start radius")?,
+        None => map.outer_radius / SQRT_2,
+    };
+    let target_radius = match arguments.value_of("target-radius") {
+        Some(target_radius) => target_radius.parse::<f64>().chain_err(|| "invalid target radius")?,
+        None => map.inner_radius * width.max(height) as f64 / 2.0,
+    };
+
+    if frame_count == 0 || fps == 0 {
+        bail!("frames and fps have to be positive");
+    }
+
+    if start_radius <= 0.0 || target_radius <= 0.0 {
+        bail!("radii have to be positive");
+    }
+
+    let frames = zoom_frames(map.center, start_radius, map.center, target_radius, frame_count, easing, map.max_iterations, 0.0);
+
+    let mut output = frame_output(arguments, width, height, frames.len(), fps)?;
+
+    // Reprojecting is cheap, so whole frames are distributed
+    render_frames(frames.len(), FrameParallelism::Frames, &mut output, |i| {
+        Ok((map.reprojected(&strip, frames[i].radius, width, height), vec![(SOFTWARE_KEYWORD.to_string(), software())]))
+    })?;
+
+    output.finish()
+}
+
+
+impl ExponentialMap {
+    // The height follows from the width and the depth, the inner radius is rounded down to a whole row
+    pub fn new(center: Complex64, outer_radius: f64, inner_radius: f64, width: u32, max_iterations: u8) -> ExponentialMap {
+        let row_height = 2.0 * PI / width as f64;
+        let height = ((outer_radius / inner_radius).ln() / row_height).ceil().max(1.0) as u32;
+
+        ExponentialMap {
+            center,
+            outer_radius,
+            inner_radius: outer_radius * (-(height as f64) * row_height).exp(),
+            width,
+            height,
+            max_iterations,
+        }
+    }
+
+
+    pub fn mandelbrot(&self) -> Mandelbrot {
+        let region = Region::new_for_corner(Complex64::new(0.0, -self.outer_radius.ln()), 2.0 * PI, (self.outer_radius / self.inner_radius).ln());
+
+        Mandelbrot::new(region, self.width, self.height, self.max_iterations).with_exponential_map(self.center)
+    }
+
+
+    // Samples the strip for every pixel of a view of the given radius around the center
+    pub fn reprojected(&self, strip: &[u8], radius: f64, width: u32, height: u32) -> Vec<u8> {
+        let pixels_per_radian = self.width as f64 / (2.0 * PI);
+        let mut pixels = Vec::with_capacity((width as usize) * (height as usize));
+
+        for y in 0..height {
+            for x in 0..width {
+                // Same pixel positions as Mandelbrot::point_for_pixel
+                let offset = Complex64::new(-radius + (x as f64) * 2.0 * radius / (width as f64),
+                                            -radius + (y as f64) * 2.0 * radius / (height as f64));
+                let (distance, angle) = offset.to_polar();
+
+                pixels.push(self.sample(strip, angle * pixels_per_radian, (self.outer_radius / distance).ln() * pixels_per_radian));
+            }
+        }
+
+        pixels
+    }
+
+
+    // Bilinear, wrapping around across and clamped at the outer and inner radius down
+    fn sample(&self, strip: &[u8], x: f64, y: f64) -> u8 {
+        let width = self.width as usize;
+        let x = x.rem_euclid(self.width as f64);
+        let y = if y.is_nan() { 0.0 } else { y.max(0.0).min((self.height - 1) as f64) };
+
+        let (x0, y0) = (x.floor() as usize % width, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1) % width, (y0 + 1).min(self.height as usize - 1));
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+
+        let value = |x: usize, y: usize| strip[y * width + x] as f64;
+        let top = value(x0, y0) * (1.0 - fx) + value(x1, y0) * fx;
+        let bottom = value(x0, y1) * (1.0 - fx) + value(x1, y1) * fx;
+
+        (top * (1.0 - fy) + bottom * fy).round() as u8
+    }
+
+
+    pub fn to_text_chunks(&self) -> Vec<(String, String)> {
+        let parameters = vec![
+            ("projection", PROJECTION.to_string()),
+            ("center-re", self.center.re.to_string()),
+            ("center-im", self.center.im.to_string()),
+            ("outer-radius", self.outer_radius.to_string()),
+            ("inner-radius", self.inner_radius.to_string()),
+            ("max-iterations", self.max_iterations.to_string()),
+        ];
+
+        let mut chunks = vec![(SOFTWARE_KEYWORD.to_string(), software())];
+        chunks.extend(parameters.into_iter().map(|(keyword, text)| (format!("{}{}", KEYWORD_PREFIX, keyword), text)));
+
+        chunks
+    }
+
+
+    pub fn from_text_chunks(chunks: &[(String, String)], width: u32, height: u32) -> Result<ExponentialMap> {
+        let text = |keyword: &str| -> Result<&str> {
+            let keyword = format!("{}{}", KEYWORD_PREFIX, keyword);
+
+            chunks.iter()
+                .find(|chunk| chunk.0 == keyword)
+                .map(|chunk| chunk.1.as_str())
+                .ok_or_else(|| format!("missing {} chunk", keyword).into())
+        };
+
+        if text("projection")? != PROJECTION {
+            bail!("not an exponential map strip");
+        }
+
+        let center_re = text("center-re")?.parse::<f64>().chain_err(|| "invalid center-re")?;
+        let center_im = text("center-im")?.parse::<f64>().chain_err(|| "invalid center-im")?;
+
+        Ok(ExponentialMap {
+            center: Complex64::new(center_re, center_im),
+            outer_radius: text("outer-radius")?.parse::<f64>().chain_err(|| "invalid outer radius")?,
+            inner_radius: text("inner-radius")?.parse::<f64>().chain_err(|| "invalid inner radius")?,
+            width,
+            height,
+            max_iterations: parsed_max_iterations(text("max-iterations")?)?,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fractal::pixel::Pixel;
+
+    #[test]
+    fn new_keeps_rows_as_high_as_columns_are_wide() {
+        // arrange
+        let center = Complex64::new(-0.74548, 0.11669);
+
+        // act
+        let map = ExponentialMap::new(center, 1.0, 0.001, 360, 100);
+        let mandelbrot = map.mandelbrot();
+
+        // assert
+        assert_eq!(map.height, 396);
+        assert!(map.inner_radius <= 0.001);
+        assert!((mandelbrot.region.height() / map.height as f64 - mandelbrot.region.width() / map.width as f64).abs() < 1e-12);
+    }
+
+    #[test]
+    fn mandelbrot_maps_columns_to_angles_and_rows_to_distances() {
+        // arrange
+        let center = Complex64::new(-0.5, 0.0);
+        let map = ExponentialMap::new(center, 2.0, 0.01, 4, 100);
+
+        // act
+        let mandelbrot = map.mandelbrot();
+        let top_left = mandelbrot.point_for_pixel(&Pixel { x: 0, y: 0 });
+        let top_second = mandelbrot.point_for_pixel(&Pixel { x: 1, y: 0 });
+        let next_row = mandelbrot.point_for_pixel(&Pixel { x: 0, y: 1 });
+
+        // assert
+        assert!((top_left - Complex64::new(1.5, 0.0)).norm() < 1e-12);
+        assert!((top_second - Complex64::new(-0.5, 2.0)).norm() < 1e-12);
+        assert!(((next_row - center).norm() - 2.0 * (-PI / 2.0).exp()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn reprojected_matches_direct_render() {
+        // arrange
+        let center = Complex64::new(-0.74548, 0.11669);
+        let radius = 0.01;
+        let map = ExponentialMap::new(center, radius * SQRT_2, radius / 64.0, 512, 100);
+        let engine = SimpleMandelbrotEngine::new(false);
+        let (strip, _) = map.mandelbrot().calculate(&engine);
+        let (direct, _) = Mandelbrot::new(Region::new_for_center(center, radius), 64, 64, 100).calculate(&engine);
+
+        // act
+        let reprojected = map.reprojected(&strip, radius, 64, 64);
+
+        // assert
+        let matching = direct.iter().zip(reprojected.iter())
+            .filter(|&(direct, reprojected)| (*direct as i32 - *reprojected as i32).abs() <= 2)
+            .count();
+
+        assert!(matching > direct.len() * 9 / 10, "{} of {} pixels match", matching, direct.len());
+    }
+
+    #[test]
+    fn from_text_chunks_restores_map() {
+        // arrange
+        let map = ExponentialMap::new(Complex64::new(-0.74548, 0.11669), 0.02, 0.00001, 512, 200);
+
+        // act
+        let restored = ExponentialMap::from_text_chunks(&map.to_text_chunks(), map.width, map.height).unwrap();
+
+        // assert
+        assert_eq!(restored, map);
+    }
+}
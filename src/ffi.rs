@@ -0,0 +1,318 @@
+// C interface of the renderer, the header include/fractal.h is generated from this module by cbindgen (see tests/ffi.rs).
+// The functions accept null pointers, any other pointer has to be valid and engines must only be freed once.
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use num::complex::Complex64;
+
+use fractal::prelude::*;
+
+
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum FractalStatus {
+    FractalOk = 0,
+    FractalNullPointer = 1,
+    FractalInvalidArgument = 2,
+    FractalBufferTooSmall = 3,
+    FractalUnknownName = 4,
+    FractalPanic = 5,
+}
+
+
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum FractalEngineType {
+    FractalEngineSimple = 0,
+    FractalEngineRecursive = 1,
+}
+
+
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct FractalRegion {
+    pub min_re: f64,
+    pub max_re: f64,
+    pub min_im: f64,
+    pub max_im: f64,
+}
+
+
+// What Mandelbrot::calculate renders, the engine is created by fractal_engine_new
+#[repr(C)]
+pub struct FractalParams {
+    pub region: FractalRegion,
+    pub width: u32,
+    pub height: u32,
+    pub max_iterations: u8,
+    // Rotation of the view around the center of the region (in radians)
+    pub rotation: f64,
+    // Renders the Julia set of julia_re + julia_im * i instead of the Mandelbrot set
+    pub julia: bool,
+    pub julia_re: f64,
+    pub julia_im: f64,
+    pub engine: *const FractalEngine,
+}
+
+
+#[repr(C)]
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct FractalStats {
+    pub escape_time_calls: u64,
+    pub iterations: u64,
+    pub pixels_inside: u64,
+    pub pixels_outside: u64,
+    pub windows_filled: u64,
+    pub windows_calculated: u64,
+}
+
+
+// Opaque handle of an engine
+pub struct FractalEngine {
+    engine: Box<dyn MandelbrotEngine>,
+}
+
+
+impl FractalStatus {
+    fn for_value(value: u32) -> Option<FractalStatus> {
+        [FractalStatus::FractalOk, FractalStatus::FractalNullPointer, FractalStatus::FractalInvalidArgument, FractalStatus::FractalBufferTooSmall,
+         FractalStatus::FractalUnknownName, FractalStatus::FractalPanic]
+            .iter()
+            .cloned()
+            .find(|status| *status as u32 == value)
+    }
+}
+
+
+impl FractalEngineType {
+    fn for_value(value: u32) -> Option<FractalEngineType> {
+        [FractalEngineType::FractalEngineSimple, FractalEngineType::FractalEngineRecursive]
+            .iter()
+            .cloned()
+            .find(|engine_type| *engine_type as u32 == value)
+    }
+}
+
+
+impl FractalRegion {
+    fn is_valid(&self) -> bool {
+        [self.min_re, self.max_re, self.min_im, self.max_im].iter().all(|bound| bound.is_finite())
+            && self.min_re < self.max_re
+            && self.min_im < self.max_im
+    }
+
+
+    fn region(&self) -> Region {
+        Region::new(self.min_re, self.max_re, self.min_im, self.max_im)
+    }
+
+
+    fn for_region(region: Region) -> FractalRegion {
+        FractalRegion {
+            min_re: region.min_re(),
+            max_re: region.min_re() + region.width(),
+            min_im: region.min_im(),
+            max_im: region.min_im() + region.height(),
+        }
+    }
+}
+
+
+impl FractalStats {
+    fn for_render_stats(stats: &RenderStats) -> FractalStats {
+        FractalStats {
+            escape_time_calls: stats.escape_time_calls,
+            iterations: stats.iterations,
+            pixels_inside: stats.pixels_inside,
+            pixels_outside: stats.pixels_outside,
+            windows_filled: stats.windows_filled,
+            windows_calculated: stats.windows_calculated,
+        }
+    }
+}
+
+
+// engine_type is a FractalEngineType, returns null for an unknown engine type or a recursive engine without band height,
+// free the engine with fractal_engine_free
+#[no_mangle]
+pub extern "C" fn fractal_engine_new(engine_type: u32, in_parallel: bool, band_height: u32) -> *mut FractalEngine {
+    let engine_type = match FractalEngineType::for_value(engine_type) {
+        Some(FractalEngineType::FractalEngineSimple) => EngineType::Simple,
+        Some(FractalEngineType::FractalEngineRecursive) if band_height > 0 => EngineType::Recursive,
+        _ => return ptr::null_mut(),
+    };
+
+    Box::into_raw(Box::new(FractalEngine { engine: engine_type.engine(in_parallel, band_height) }))
+}
+
+
+#[no_mangle]
+pub unsafe extern "C" fn fractal_engine_free(engine: *mut FractalEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+
+// Looks up a region type by its name on the command line, e.g. "SeaHorseValley"
+#[no_mangle]
+pub unsafe extern "C" fn fractal_region_for_name(name: *const c_char, region: *mut FractalRegion) -> FractalStatus {
+    if name.is_null() || region.is_null() {
+        return FractalStatus::FractalNullPointer;
+    }
+
+    let region_type = CStr::from_ptr(name).to_str().ok().and_then(RegionType::for_name);
+
+    match region_type {
+        Some(region_type) => {
+            *region = FractalRegion::for_region(Region::new_for_type(region_type));
+            FractalStatus::FractalOk
+        },
+        None => FractalStatus::FractalUnknownName,
+    }
+}
+
+
+// A square region reaching radius from the center in each direction
+#[no_mangle]
+pub unsafe extern "C" fn fractal_region_for_center(re: f64, im: f64, radius: f64, region: *mut FractalRegion) -> FractalStatus {
+    if region.is_null() {
+        return FractalStatus::FractalNullPointer;
+    }
+
+    if radius.is_nan() || radius <= 0.0 || !re.is_finite() || !im.is_finite() {
+        return FractalStatus::FractalInvalidArgument;
+    }
+
+    *region = FractalRegion::for_region(Region::new_for_center(Complex64::new(re, im), radius));
+    FractalStatus::FractalOk
+}
+
+
+// Writes the escape times of the width * height pixels row by row into pixels, stats may be null
+#[no_mangle]
+pub unsafe extern "C" fn fractal_render(params: *const FractalParams, pixels: *mut u8, pixels_len: usize, stats: *mut FractalStats) -> FractalStatus {
+    if params.is_null() || pixels.is_null() || (*params).engine.is_null() {
+        return FractalStatus::FractalNullPointer;
+    }
+
+    let params = &*params;
+
+    if params.width == 0 || params.height == 0 || params.max_iterations == 0 || !params.region.is_valid() {
+        return FractalStatus::FractalInvalidArgument;
+    }
+
+    if pixels_len < params.width as usize * params.height as usize {
+        return FractalStatus::FractalBufferTooSmall;
+    }
+
+    let pixels = slice::from_raw_parts_mut(pixels, pixels_len);
+    let engine = &(*params.engine).engine;
+
+    // Panics must not unwind into the caller
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mandelbrot = Mandelbrot::new(params.region.region(), params.width, params.height, params.max_iterations)
+            .with_rotation(params.rotation);
+        let mandelbrot = if params.julia { mandelbrot.with_julia_constant(Complex64::new(params.julia_re, params.julia_im)) } else { mandelbrot };
+
+        mandelbrot.calculate(&**engine)
+    }));
+
+    match result {
+        Ok((calculated_pixels, render_stats)) => {
+            pixels[..calculated_pixels.len()].copy_from_slice(&calculated_pixels);
+
+            if !stats.is_null() {
+                *stats = FractalStats::for_render_stats(&render_stats);
+            }
+
+            FractalStatus::FractalOk
+        },
+        Err(_) => FractalStatus::FractalPanic,
+    }
+}
+
+
+// Static, null terminated description of a status (a FractalStatus), unknown values are described as invalid arguments
+#[no_mangle]
+pub extern "C" fn fractal_status_message(status: u32) -> *const c_char {
+    let status = FractalStatus::for_value(status).unwrap_or(FractalStatus::FractalInvalidArgument);
+
+    let message: &'static [u8] = match status {
+        FractalStatus::FractalOk => b"ok\0",
+        FractalStatus::FractalNullPointer => b"null pointer\0",
+        FractalStatus::FractalInvalidArgument => b"invalid argument\0",
+        FractalStatus::FractalBufferTooSmall => b"buffer too small\0",
+        FractalStatus::FractalUnknownName => b"unknown name\0",
+        FractalStatus::FractalPanic => b"render failed\0",
+    };
+
+    message.as_ptr() as *const c_char
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_matches_mandelbrot_calculate() {
+        // arrange
+        let engine = fractal_engine_new(FractalEngineType::FractalEngineRecursive as u32, false, 8);
+        let mut region = FractalRegion { min_re: 0.0, max_re: 0.0, min_im: 0.0, max_im: 0.0 };
+        let mut pixels = vec![0; 40 * 30];
+        let mut stats = FractalStats::default();
+        let expected = Mandelbrot::new(Region::new_for_type(RegionType::SeaHorseValley), 40, 30, 100).calculate(&SimpleMandelbrotEngine::new(false)).0;
+
+        // act
+        let status = unsafe {
+            fractal_region_for_name(b"SeaHorseValley\0".as_ptr() as *const c_char, &mut region);
+            let params = FractalParams { region, width: 40, height: 30, max_iterations: 100, rotation: 0.0, julia: false, julia_re: 0.0, julia_im: 0.0, engine };
+            let status = fractal_render(&params, pixels.as_mut_ptr(), pixels.len(), &mut stats);
+            fractal_engine_free(engine);
+            status
+        };
+
+        // assert
+        assert_eq!(status, FractalStatus::FractalOk);
+        assert_eq!(pixels, expected);
+        assert_eq!(stats.pixels_inside + stats.pixels_outside, 40 * 30);
+    }
+
+    #[test]
+    fn render_rejects_inverted_and_infinite_regions() {
+        // arrange
+        let engine = fractal_engine_new(FractalEngineType::FractalEngineSimple as u32, false, 0);
+        let mut pixels = vec![0; 8 * 8];
+        let regions = [
+            FractalRegion { min_re: 1.0, max_re: -1.0, min_im: -1.0, max_im: 1.0 },
+            FractalRegion { min_re: -1.0, max_re: 1.0, min_im: -1.0, max_im: -1.0 },
+            FractalRegion { min_re: f64::NEG_INFINITY, max_re: 1.0, min_im: -1.0, max_im: 1.0 },
+            FractalRegion { min_re: -1.0, max_re: 1.0, min_im: f64::NAN, max_im: 1.0 },
+        ];
+
+        // act
+        let statuses: Vec<_> = regions.iter()
+            .map(|region| {
+                let params = FractalParams { region: *region, width: 8, height: 8, max_iterations: 10, rotation: 0.0, julia: false, julia_re: 0.0, julia_im: 0.0, engine };
+                unsafe { fractal_render(&params, pixels.as_mut_ptr(), pixels.len(), ptr::null_mut()) }
+            })
+            .collect();
+        unsafe { fractal_engine_free(engine) };
+
+        // assert
+        assert!(statuses.iter().all(|status| *status == FractalStatus::FractalInvalidArgument));
+    }
+
+    #[test]
+    fn unknown_enum_values_are_rejected() {
+        // act & assert
+        assert!(fractal_engine_new(2, false, 8).is_null());
+        assert_eq!(unsafe { CStr::from_ptr(fractal_status_message(6)) }.to_str(), Ok("invalid argument"));
+    }
+}
@@ -0,0 +1,74 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use errors::*;
+use fractal::nucleus::{self, BoxPeriod};
+use animate::region_radius;
+use super::parsed_center_and_radius;
+
+
+// Below this radius the nucleus is refined in double-double precision
+const HIGH_PRECISION_RADIUS: f64 = 1e-10;
+// The view radius relative to the size estimate, the whole set (size 1) fits into a radius of 2 around its nucleus 0
+const VIEW_RADIUS_PER_SIZE: f64 = 2.0;
+// Renders in f64 fall apart when pixels get near the precision of the center
+const MIN_RELATIVE_VIEW_RADIUS: f64 = 1e-13;
+
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("find-minibrot")
+        .about("Finds the minibrot of the lowest period in a region and prints the center and radius to render it")
+        .arg(Arg::with_name("near")
+            .long("near")
+            .help("Specifies the center and radius of the region to search (format: <real>/<imag>@radius)")
+            .required(true)
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("CENTER-AND-RADIUS"))
+        .arg(Arg::with_name("max-period")
+            .long("max-period")
+            .help("Gives up beyond this period")
+            .takes_value(true)
+            .value_name("MAX-PERIOD")
+            .default_value("100000"))
+}
+
+
+pub fn run(arguments: &ArgMatches) -> Result<()> {
+    let region = parsed_center_and_radius(arguments.value_of("near").unwrap()).chain_err(|| "parsing region failed")?;
+    let max_period = arguments.value_of("max-period").unwrap().parse::<u32>().chain_err(|| "invalid max period")?;
+
+    let period = match nucleus::box_period(&region, max_period) {
+        BoxPeriod::Found(period) => period,
+        BoxPeriod::Escaped(period) => {
+            bail!("the orbit of a corner escaped at period {} before the corners surrounded 0, the region may lie outside the set", period)
+        },
+        // All corners inside an atom keep circling without ever surrounding 0
+        BoxPeriod::NotFound => {
+            bail!("the corners of the region surrounded 0 at no period up to {}, they may lie inside an atom, try a smaller radius", max_period)
+        },
+    };
+
+    let nucleus = nucleus::nucleus_in_region(&region, period, region_radius(&region) < HIGH_PRECISION_RADIUS)
+        .ok_or_else(|| format!("Newton's method found no nucleus of period {} in the region", period))?;
+
+    let size = nucleus::size(nucleus, period);
+
+    info!(target: "fractal::find_minibrot",
+          period = period,
+          size = size.norm(),
+          angle = size.arg().to_degrees();
+          "found nucleus");
+
+    let view_radius = size.norm() * VIEW_RADIUS_PER_SIZE;
+
+    if view_radius < MIN_RELATIVE_VIEW_RADIUS * nucleus.norm() {
+        warn!(target: "fractal::find_minibrot", radius = view_radius; "the minibrot is too small to be rendered in f64 precision");
+    }
+
+    println!("period:  {}", period);
+    println!("nucleus: {}/{}", nucleus.re, nucleus.im);
+    println!("size:    {:.3e}", size.norm());
+    println!("-c {}/{}@{:.3e}", nucleus.re, nucleus.im, view_radius);
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use errors::*;
+use fractal::misiurewicz::MisiurewiczPoint;
+use super::parsed_center_and_radius;
+
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("find-misiurewicz")
+        .about("Finds the Misiurewicz point (the center of a spiral) of the lowest preperiod and period in a region and prints the center and radius to render it")
+        .arg(Arg::with_name("near")
+            .long("near")
+            .help("Specifies the center and radius of the region to search (format: <real>/<imag>@radius)")
+            .required(true)
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("CENTER-AND-RADIUS"))
+        .arg(Arg::with_name("preperiod")
+            .long("preperiod")
+            .help("Only searches for points of this preperiod (at least 2) instead of detecting it")
+            .takes_value(true)
+            .value_name("PREPERIOD"))
+        .arg(Arg::with_name("period")
+            .long("period")
+            .help("Only searches for points of this period (at least 1) instead of detecting it")
+            .takes_value(true)
+            .value_name("PERIOD"))
+        .arg(Arg::with_name("max-preperiod")
+            .long("max-preperiod")
+            .help("Gives up beyond this preperiod when detecting it")
+            .takes_value(true)
+            .value_name("MAX-PREPERIOD")
+            .default_value("64"))
+        .arg(Arg::with_name("max-period")
+            .long("max-period")
+            .help("Gives up beyond this period when detecting it")
+            .takes_value(true)
+            .value_name("MAX-PERIOD")
+            .default_value("64"))
+}
+
+
+pub fn run(arguments: &ArgMatches) -> Result<()> {
+    let region = parsed_center_and_radius(arguments.value_of("near").unwrap()).chain_err(|| "parsing region failed")?;
+    let max_preperiod = arguments.value_of("max-preperiod").unwrap().parse::<u32>().chain_err(|| "invalid max preperiod")?;
+    let max_period = arguments.value_of("max-period").unwrap().parse::<u32>().chain_err(|| "invalid max period")?;
+
+    if max_preperiod < 2 {
+        bail!("max preperiod has to be at least 2");
+    }
+
+    if max_period == 0 {
+        bail!("max period has to be positive");
+    }
+
+    let preperiods = match arguments.value_of("preperiod") {
+        Some(preperiod) => {
+            let preperiod = preperiod.parse::<u32>().chain_err(|| "invalid preperiod")?;
+
+            if preperiod < 2 {
+                bail!("preperiod has to be at least 2");
+            }
+
+            preperiod..=preperiod
+        },
+        None => 2..=max_preperiod,
+    };
+    let periods = match arguments.value_of("period") {
+        Some(period) => {
+            let period = period.parse::<u32>().chain_err(|| "invalid period")?;
+
+            if period == 0 {
+                bail!("period has to be positive");
+            }
+
+            period..=period
+        },
+        None => 1..=max_period,
+    };
+
+    let point = MisiurewiczPoint::find_in_region(&region, preperiods, periods)
+        .ok_or("Newton's method found no Misiurewicz point of the preperiods and periods in the region")?;
+    let multiplier = point.multiplier();
+
+    println!("preperiod:  {}", point.preperiod);
+    println!("period:     {}", point.period);
+    println!("point:      {}/{}", point.point.re, point.point.im);
+    println!("multiplier: {:.3e} at {:.1} degrees", multiplier.norm(), multiplier.arg().to_degrees());
+    println!("-c {}/{}@{:.3e}", point.point.re, point.point.im, point.radius());
+
+    Ok(())
+}
@@ -0,0 +1,158 @@
+// Scales smooth escape times to big endian 16 bit samples (as expected by PNG) with the brightness of the 8 bit escape
+// times, the fractional part of the escape times gives the additional precision
+pub fn sixteen_bit_samples(smooth_values: &[f32], samples_per_pixel: usize) -> Vec<u8> {
+    let mut data: Vec<u8> = Vec::with_capacity(smooth_values.len() * samples_per_pixel * 2);
+
+    for &value in smooth_values {
+        let sample = (value * 257.0).round().clamp(0.0, 65535.0) as u16;
+
+        for _ in 0..samples_per_pixel {
+            data.push((sample >> 8) as u8);
+            data.push((sample & 0xff) as u8);
+        }
+    }
+
+    data
+}
+
+
+// Shifts the gray levels of escaping points cyclically, points inside the set keep their level
+pub fn cycle_palette(pixels: &mut [u8], palette_offset: f64, max_iterations: u8) {
+    if max_iterations < 2 {
+        return;
+    }
+
+    let period = (max_iterations - 1) as i64;
+    let offset = (palette_offset.round() as i64).rem_euclid(period);
+
+    if offset == 0 {
+        return;
+    }
+
+    for pixel in pixels.iter_mut().filter(|pixel| **pixel < max_iterations) {
+        *pixel = (1 + (*pixel as i64 - 1 + offset).rem_euclid(period)) as u8;
+    }
+}
+
+
+// Opaque RGBA quadruples of the gray levels, i.e. for the image data of an HTML canvas
+pub fn rgba(pixels: &[u8]) -> Vec<u8> {
+    pixels.iter().flat_map(|&level| vec![level, level, level, 255]).collect()
+}
+
+
+// Evenly spaced gray levels from black to white as RGB triples
+pub fn gray_palette(colors: u16) -> Vec<u8> {
+    (0..colors)
+        .flat_map(|i| {
+            let level = gray_level(i, colors) as u8;
+            vec![level, level, level]
+        })
+        .collect()
+}
+
+
+fn gray_level(index: u16, colors: u16) -> f32 {
+    (index as f32 * 255.0 / (colors - 1) as f32).round()
+}
+
+
+// Maps the gray levels to palette indices, optionally diffusing the rounding error Floyd-Steinberg style
+pub fn quantized(pixels: &[u8], width: usize, colors: u16, dither: bool) -> Vec<u8> {
+    let step = 255.0 / (colors - 1) as f32;
+    let mut errors = vec![0.0f32; if dither { pixels.len() } else { 0 }];
+
+    (0..pixels.len())
+        .map(|i| {
+            let value = pixels[i] as f32 + if dither { errors[i] } else { 0.0 };
+            let index = (value / step).round().max(0.0).min((colors - 1) as f32) as u16;
+
+            if dither {
+                let error = value - gray_level(index, colors);
+                let x = i % width;
+                let below = i + width;
+
+                if x + 1 < width {
+                    errors[i + 1] += error * 7.0 / 16.0;
+                }
+
+                if below < pixels.len() {
+                    if x > 0 {
+                        errors[below - 1] += error * 3.0 / 16.0;
+                    }
+
+                    errors[below] += error * 5.0 / 16.0;
+
+                    if x + 1 < width {
+                        errors[below + 1] += error / 16.0;
+                    }
+                }
+            }
+
+            index as u8
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sixteen_bit_samples_scales_and_repeats_samples() {
+        // act
+        let data = sixteen_bit_samples(&[-0.5, 1.5, 255.0], 3);
+
+        // assert
+        assert_eq!(data, vec!(0, 0, 0, 0, 0, 0, 1, 130, 1, 130, 1, 130, 255, 255, 255, 255, 255, 255));
+    }
+
+    #[test]
+    fn rgba_repeats_gray_levels_with_opaque_alpha() {
+        // act
+        let data = rgba(&[0, 128]);
+
+        // assert
+        assert_eq!(data, vec!(0, 0, 0, 255, 128, 128, 128, 255));
+    }
+
+    #[test]
+    fn cycle_palette_wraps_escaping_points_and_keeps_inside_points() {
+        // arrange
+        let mut pixels: Vec<u8> = vec!(1, 3, 4, 5);
+
+        // act
+        cycle_palette(&mut pixels, 2.0, 5);
+
+        // assert
+        assert_eq!(pixels, vec!(3, 1, 2, 5));
+    }
+
+    #[test]
+    fn quantized_with_256_colors_keeps_gray_levels() {
+        // arrange
+        let pixels: Vec<u8> = (0..=255).collect();
+
+        // act
+        let indices = quantized(&pixels, 16, 256, true);
+
+        // assert
+        assert_eq!(indices, pixels);
+    }
+
+    #[test]
+    fn quantized_with_dithering_preserves_average_gray_level() {
+        // arrange
+        let pixels = vec![100u8; 64 * 64];
+
+        // act
+        let rounded = quantized(&pixels, 64, 2, false);
+        let dithered = quantized(&pixels, 64, 2, true);
+
+        // assert
+        assert!(rounded.iter().all(|&index| index == 0));
+        let white = dithered.iter().filter(|&&index| index == 1).count() as f64;
+        assert!((white / dithered.len() as f64 - 100.0 / 255.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,201 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use num::complex::Complex64;
+
+
+// An unevaluated sum of two f64 with about 106 bits of mantissa, for calculations where f64 rounding dominates
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct DoubleDouble {
+    hi: f64,
+    lo: f64,
+}
+
+
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct DoubleDoubleComplex {
+    pub re: DoubleDouble,
+    pub im: DoubleDouble,
+}
+
+
+impl DoubleDouble {
+    pub fn new(value: f64) -> DoubleDouble {
+        DoubleDouble { hi: value, lo: 0.0 }
+    }
+
+
+    pub fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+}
+
+
+// Exact sum of a and b, the rounding error goes into lo
+fn two_sum(a: f64, b: f64) -> DoubleDouble {
+    let hi = a + b;
+    let b_part = hi - a;
+    let lo = (a - (hi - b_part)) + (b - b_part);
+
+    DoubleDouble { hi, lo }
+}
+
+
+// Like two_sum, but only for |a| >= |b|
+fn quick_two_sum(a: f64, b: f64) -> DoubleDouble {
+    let hi = a + b;
+
+    DoubleDouble { hi, lo: b - (hi - a) }
+}
+
+
+// Exact product of a and b thanks to the fused multiply-add
+fn two_product(a: f64, b: f64) -> DoubleDouble {
+    let hi = a * b;
+
+    DoubleDouble { hi, lo: a.mul_add(b, -hi) }
+}
+
+
+impl Add for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn add(self, other: DoubleDouble) -> DoubleDouble {
+        let high_sum = two_sum(self.hi, other.hi);
+        let low_sum = two_sum(self.lo, other.lo);
+        let sum = quick_two_sum(high_sum.hi, high_sum.lo + low_sum.hi);
+
+        quick_two_sum(sum.hi, sum.lo + low_sum.lo)
+    }
+}
+
+
+impl Neg for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn neg(self) -> DoubleDouble {
+        DoubleDouble { hi: -self.hi, lo: -self.lo }
+    }
+}
+
+
+impl Sub for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn sub(self, other: DoubleDouble) -> DoubleDouble {
+        self + -other
+    }
+}
+
+
+impl Mul for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn mul(self, other: DoubleDouble) -> DoubleDouble {
+        let product = two_product(self.hi, other.hi);
+
+        quick_two_sum(product.hi, product.lo + self.hi * other.lo + self.lo * other.hi)
+    }
+}
+
+
+impl Div for DoubleDouble {
+    type Output = DoubleDouble;
+
+    // Long division with three f64 quotient digits
+    fn div(self, other: DoubleDouble) -> DoubleDouble {
+        let first = self.hi / other.hi;
+        let remainder = self - other * DoubleDouble::new(first);
+        let second = remainder.hi / other.hi;
+        let remainder = remainder - other * DoubleDouble::new(second);
+        let third = remainder.hi / other.hi;
+
+        quick_two_sum(first, second) + DoubleDouble::new(third)
+    }
+}
+
+
+impl DoubleDoubleComplex {
+    pub fn new(value: Complex64) -> DoubleDoubleComplex {
+        DoubleDoubleComplex { re: DoubleDouble::new(value.re), im: DoubleDouble::new(value.im) }
+    }
+
+
+    pub fn to_complex64(self) -> Complex64 {
+        Complex64::new(self.re.to_f64(), self.im.to_f64())
+    }
+}
+
+
+impl Add for DoubleDoubleComplex {
+    type Output = DoubleDoubleComplex;
+
+    fn add(self, other: DoubleDoubleComplex) -> DoubleDoubleComplex {
+        DoubleDoubleComplex { re: self.re + other.re, im: self.im + other.im }
+    }
+}
+
+
+impl Sub for DoubleDoubleComplex {
+    type Output = DoubleDoubleComplex;
+
+    fn sub(self, other: DoubleDoubleComplex) -> DoubleDoubleComplex {
+        DoubleDoubleComplex { re: self.re - other.re, im: self.im - other.im }
+    }
+}
+
+
+impl Mul for DoubleDoubleComplex {
+    type Output = DoubleDoubleComplex;
+
+    fn mul(self, other: DoubleDoubleComplex) -> DoubleDoubleComplex {
+        DoubleDoubleComplex {
+            re: self.re * other.re - self.im * other.im,
+            im: self.re * other.im + self.im * other.re,
+        }
+    }
+}
+
+
+impl Div for DoubleDoubleComplex {
+    type Output = DoubleDoubleComplex;
+
+    fn div(self, other: DoubleDoubleComplex) -> DoubleDoubleComplex {
+        let norm_sqr = other.re * other.re + other.im * other.im;
+
+        DoubleDoubleComplex {
+            re: (self.re * other.re + self.im * other.im) / norm_sqr,
+            im: (self.im * other.re - self.re * other.im) / norm_sqr,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sum_keeps_what_f64_rounds_away() {
+        // arrange
+        let one = DoubleDouble::new(1.0);
+        let tiny = DoubleDouble::new(1e-20);
+
+        // act
+        let difference = (one + tiny) - one;
+
+        // assert
+        assert_eq!(difference.to_f64(), 1e-20);
+    }
+
+    #[test]
+    fn division_inverts_multiplication() {
+        // arrange
+        let third = DoubleDouble::new(1.0) / DoubleDouble::new(3.0);
+
+        // act
+        let error = third * DoubleDouble::new(3.0) - DoubleDouble::new(1.0);
+
+        // assert
+        assert!(error.to_f64().abs() < 1e-31);
+    }
+}
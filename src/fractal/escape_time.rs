@@ -1,17 +1,35 @@
 use num::complex::Complex64;
 
 
+// Large bailout radius (squared) for smooth escape times, which keeps the logarithmic correction accurate
+const SMOOTH_BAILOUT_NORM_SQR: f64 = 256.0 * 256.0;
+
+
+// The Mandelbrot set iterates z^2+c starting at c, a Julia set starts at the point and adds a constant instead
 pub trait EscapeTime {
     fn escape_time(&self, max_iterations: u8) -> u8;
+    fn smooth_escape_time(&self, max_iterations: u8) -> f32;
+    fn julia_escape_time(&self, constant: &Complex64, max_iterations: u8) -> u8;
+    fn julia_smooth_escape_time(&self, constant: &Complex64, max_iterations: u8) -> f32;
 }
 
 
 impl EscapeTime for Complex64 {
     fn escape_time(&self, max_iterations: u8) -> u8 {
+        self.julia_escape_time(self, max_iterations)
+    }
+
+
+    fn smooth_escape_time(&self, max_iterations: u8) -> f32 {
+        self.julia_smooth_escape_time(self, max_iterations)
+    }
+
+
+    fn julia_escape_time(&self, constant: &Complex64, max_iterations: u8) -> u8 {
         let mut point = *self;
 
         for escape in 1..max_iterations {
-            point = point * point + self;
+            point = point * point + constant;
 
             if point.norm_sqr() > 4.0 {
                 return escape;
@@ -20,6 +38,25 @@ impl EscapeTime for Complex64 {
 
         max_iterations
     }
+
+
+    fn julia_smooth_escape_time(&self, constant: &Complex64, max_iterations: u8) -> f32 {
+        let mut point = *self;
+
+        for escape in 1..max_iterations {
+            point = point * point + constant;
+
+            let norm_sqr = point.norm_sqr();
+            if norm_sqr > SMOOTH_BAILOUT_NORM_SQR {
+                let log_modulus = norm_sqr.ln() / 2.0;
+                let correction = (log_modulus / 2f64.ln()).log2();
+
+                return ((escape as f64) + 1.0 - correction) as f32;
+            }
+        }
+
+        max_iterations as f32
+    }
 }
 
 
@@ -62,4 +99,52 @@ mod tests {
         // assert
         assert_eq!(escape, 200);
     }
+
+    #[test]
+    fn smooth_escape_time_for_origin_returns_max_iterations() {
+        // arrange
+        let c = Complex64::new(0.0, 0.0);
+
+        // act
+        let escape = c.smooth_escape_time(200);
+
+        // assert
+        assert_eq!(escape, 200.0);
+    }
+
+    #[test]
+    fn julia_escape_time_with_point_as_constant_equals_escape_time() {
+        // arrange
+        let c = Complex64::new(-0.74548, 0.11669);
+
+        // act
+        let escape = c.julia_escape_time(&c, 200);
+
+        // assert
+        assert_eq!(escape, c.escape_time(200));
+    }
+
+    #[test]
+    fn julia_escape_time_for_origin_with_zero_constant_returns_max_iterations() {
+        // act
+        let escape = Complex64::new(0.9, 0.0).julia_escape_time(&Complex64::new(0.0, 0.0), 200);
+
+        // assert
+        assert_eq!(escape, 200);
+    }
+
+    #[test]
+    fn smooth_escape_time_increases_towards_the_set() {
+        // arrange
+        let outer = Complex64::new(0.5, 0.5);
+        let inner = Complex64::new(0.3, 0.5);
+
+        // act
+        let outer_escape = outer.smooth_escape_time(200);
+        let inner_escape = inner.smooth_escape_time(200);
+
+        // assert
+        assert!(outer_escape < inner_escape);
+        assert!(outer_escape >= outer.escape_time(200) as f32 - 1.0);
+    }
 }
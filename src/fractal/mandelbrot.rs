@@ -1,8 +1,14 @@
 use num::complex::Complex64;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 use super::region::Region;
 use super::pixel::Pixel;
 use super::mandelbrot_engine::MandelbrotEngine;
+use super::render_stats::{RenderStats, PhaseTimer};
+use super::escape_time::EscapeTime;
+use super::window::Window;
+use super::pixel_band::PixelBand;
 
 
 pub struct Mandelbrot {
@@ -10,28 +16,280 @@ pub struct Mandelbrot {
     pub width: u32,
     pub height: u32,
     pub max_iterations: u8,
+    // Rotation of the view around the center of the region (in radians)
+    pub rotation: f64,
+    // Renders the Julia set of this constant instead of the Mandelbrot set
+    pub julia_constant: Option<Complex64>,
+    // Interprets the region as log-polar coordinates around this center: the real part is the angle,
+    // the imaginary part the negative logarithm of the distance (see point_for_pixel)
+    pub exponential_map_center: Option<Complex64>,
 }
 
 
 impl Mandelbrot {
     pub fn new(region: Region, width: u32, height: u32, max_iterations: u8) -> Mandelbrot {
-        Mandelbrot { region, width, height, max_iterations }
+        Mandelbrot { region, width, height, max_iterations, rotation: 0.0, julia_constant: None, exponential_map_center: None }
     }
 
 
-    pub fn calculate(&self, engine: &MandelbrotEngine) -> Vec<u8> {
-        let mut pixels: Vec<u8> = vec![0 as u8; (self.width * self.height) as usize];
+    pub fn with_rotation(self, rotation: f64) -> Mandelbrot {
+        Mandelbrot { rotation, ..self }
+    }
+
+
+    pub fn with_julia_constant(self, julia_constant: Complex64) -> Mandelbrot {
+        Mandelbrot { julia_constant: Some(julia_constant), ..self }
+    }
+
+
+    pub fn with_exponential_map(self, center: Complex64) -> Mandelbrot {
+        Mandelbrot { exponential_map_center: Some(center), ..self }
+    }
+
+
+    pub fn calculate(&self, engine: &dyn MandelbrotEngine) -> (Vec<u8>, RenderStats) {
+        let mut pixels: Vec<u8> = vec![0; self.number_of_pixels()];
+
+        let timer = PhaseTimer::start("calculate");
+        let mut stats = engine.calculate(self, &mut pixels);
+        stats.phases.push(timer.finish());
+
+        stats.record_pixels(&pixels, self.max_iterations);
+
+        (pixels, stats)
+    }
+
+
+    // Calculates the image band by band (top to bottom) and hands each band to the consumer in order,
+    // so only a few bands are held in memory at a time
+    pub fn calculate_bands<F, E>(&self, engine: &dyn MandelbrotEngine, band_height: u32, mut consume_band: F) -> Result<RenderStats, E>
+        where F: FnMut(&[u8]) -> Result<(), E>
+    {
+        let band_height = band_height.max(1);
+        let number_of_bands = self.height.div_ceil(band_height);
+        let bands_per_batch = if engine.should_calculate_in_parallel() { number_of_threads() } else { 1 };
+
+        let windows: Vec<Window> = (0..number_of_bands)
+            .map(|i| {
+                let min_y = i * band_height;
+                Window::new(0, min_y, self.width, band_height.min(self.height - min_y))
+            })
+            .collect();
+
+        let timer = PhaseTimer::start("stream");
+        let mut stats = RenderStats::new();
+
+        for batch in windows.chunks(bands_per_batch) {
+            #[cfg(feature = "parallel")]
+            let windows = batch.par_iter();
+            #[cfg(not(feature = "parallel"))]
+            let windows = batch.iter();
+
+            let bands: Vec<(Vec<u8>, RenderStats)> = windows
+                .map(|window| self.calculate_band(engine, window))
+                .collect();
+
+            for (pixels, band_stats) in bands {
+                stats.add(&band_stats);
+                stats.record_pixels(&pixels, self.max_iterations);
+
+                consume_band(&pixels)?;
+            }
+        }
+
+        stats.phases.push(timer.finish());
+
+        Ok(stats)
+    }
+
+
+    fn calculate_band(&self, engine: &dyn MandelbrotEngine, window: &Window) -> (Vec<u8>, RenderStats) {
+        let mut pixels: Vec<u8> = vec![0; (window.width as usize) * (window.height as usize)];
+        let offset = (window.min_y as usize) * (self.width as usize);
+
+        let stats = engine.calculate_band(self, window, &mut PixelBand::new(&mut pixels, offset));
+
+        (pixels, stats)
+    }
+
+
+    // Calculates a window as an image of its own, i.e. for tiles
+    pub fn calculate_window(&self, engine: &dyn MandelbrotEngine, window: &Window) -> (Vec<u8>, RenderStats) {
+        let tile = Mandelbrot { region: self.region_for_window(window), width: window.width, height: window.height, ..*self };
+
+        tile.calculate_band(engine, &Window::new(0, 0, window.width, window.height))
+    }
+
+
+    // The region of a window, which gives the same points as the window of this view with the same rotation and
+    // exponential map: a rotated window is rotated around its own center, so that center is moved to where the
+    // rotation around the center of the view takes it
+    pub fn region_for_window(&self, window: &Window) -> Region {
+        let corner = self.unrotated_point_for_pixel(&Pixel { x: window.min_x, y: window.min_y });
+        let size = Complex64::new((window.width as f64) * self.region.width() / (self.width as f64),
+                                  (window.height as f64) * self.region.height() / (self.height as f64));
+
+        if self.exponential_map_center.is_some() || self.rotation == 0.0 {
+            return Region::new_for_corner(corner, size.re, size.im);
+        }
+
+        let center = self.rotated(corner + size / 2.0);
+
+        Region::new_for_corner(center - size / 2.0, size.re, size.im)
+    }
+
+
+    // Smooth escape times are continuous, so every pixel is calculated whatever the engine (the recursive engine only
+    // saves work for areas of equal escape times), the engine only decides whether rows are calculated in parallel
+    pub fn calculate_smooth(&self, engine: &dyn MandelbrotEngine) -> Vec<f32> {
+        self.calculate_smooth_window(engine, &Window::new(0, 0, self.width, self.height))
+    }
+
+
+    pub fn calculate_smooth_window(&self, engine: &dyn MandelbrotEngine, window: &Window) -> Vec<f32> {
+        let width = window.width as usize;
+        let mut values: Vec<f32> = vec![0.0; width * (window.height as usize)];
+        let rows_per_chunk = if engine.should_calculate_in_parallel() { 1 } else { window.height as usize };
+        let chunk_size = (rows_per_chunk * width).max(1);
+
+        #[cfg(feature = "parallel")]
+        let chunks = values.par_chunks_mut(chunk_size);
+        #[cfg(not(feature = "parallel"))]
+        let chunks = values.chunks_mut(chunk_size);
 
-        engine.calculate(&self, &mut pixels);
+        chunks.enumerate()
+            .for_each(|(i, chunk)| {
+                for (j, value) in chunk.iter_mut().enumerate() {
+                    let index = i * chunk_size + j;
+                    let pixel = Pixel { x: window.min_x + (index % width) as u32, y: window.min_y + (index / width) as u32 };
 
-        pixels
+                    *value = self.smooth_escape_time_for_pixel(&pixel);
+                }
+            });
+
+        values
+    }
+
+
+    fn number_of_pixels(&self) -> usize {
+        (self.width as usize) * (self.height as usize)
     }
 
 
     pub fn point_for_pixel(&self, pixel: &Pixel) -> Complex64 {
+        let point = self.unrotated_point_for_pixel(pixel);
+
+        // exp(i * (angle - i * log distance)) = distance * exp(i * angle), so rows going down zoom in exponentially
+        if let Some(center) = self.exponential_map_center {
+            return center + ((point + self.rotation) * Complex64::i()).exp();
+        }
+
+        self.rotated(point)
+    }
+
+
+    fn unrotated_point_for_pixel(&self, pixel: &Pixel) -> Complex64 {
         Complex64 {
             re: self.region.min_re() + (pixel.x as f64) * self.region.width() / (self.width as f64),
             im: self.region.min_im() + (pixel.y as f64) * self.region.height() / (self.height as f64),
         }
     }
+
+
+    fn rotated(&self, point: Complex64) -> Complex64 {
+        if self.rotation == 0.0 {
+            return point;
+        }
+
+        let center = Complex64::new(self.region.min_re() + self.region.width() / 2.0, self.region.min_im() + self.region.height() / 2.0);
+
+        center + (point - center) * Complex64::from_polar(1.0, self.rotation)
+    }
+
+
+    pub fn escape_time_for_pixel(&self, pixel: &Pixel) -> u8 {
+        let point = self.point_for_pixel(pixel);
+
+        match self.julia_constant {
+            Some(ref constant) => point.julia_escape_time(constant, self.max_iterations),
+            None => point.escape_time(self.max_iterations),
+        }
+    }
+
+
+    pub fn smooth_escape_time_for_pixel(&self, pixel: &Pixel) -> f32 {
+        let point = self.point_for_pixel(pixel);
+
+        match self.julia_constant {
+            Some(ref constant) => point.julia_smooth_escape_time(constant, self.max_iterations),
+            None => point.smooth_escape_time(self.max_iterations),
+        }
+    }
+}
+
+
+#[cfg(feature = "parallel")]
+fn number_of_threads() -> usize {
+    rayon::current_num_threads()
+}
+
+
+#[cfg(not(feature = "parallel"))]
+fn number_of_threads() -> usize {
+    1
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::simple_mandelbrot_engine::SimpleMandelbrotEngine;
+
+    fn window_of(pixels: &[u8], width: u32, window: &Window) -> Vec<u8> {
+        (window.min_y..window.min_y + window.height)
+            .flat_map(|y| (window.min_x..window.min_x + window.width).map(move |x| pixels[(y * width + x) as usize]))
+            .collect()
+    }
+
+    #[test]
+    fn calculate_window_of_rotated_and_exponential_map_views_matches_whole_image() {
+        // arrange
+        let engine = SimpleMandelbrotEngine::new(false);
+        let region = Region::new_for_center(Complex64::new(-0.75, 0.1), 0.5);
+        let window = Window::new(20, 8, 24, 16);
+        let views = vec!(
+            Mandelbrot::new(region, 64, 48, 100).with_rotation(0.7),
+            Mandelbrot::new(region, 64, 48, 100).with_rotation(0.7).with_exponential_map(Complex64::new(-0.75, 0.1)),
+        );
+
+        for view in views {
+            // act
+            let (pixels, _) = view.calculate(&engine);
+            let (window_pixels, _) = view.calculate_window(&engine, &window);
+
+            // assert
+            assert_eq!(window_pixels, window_of(&pixels, 64, &window));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn calculate_in_parallel_of_empty_images_returns_no_pixels() {
+        // arrange
+        let engines: Vec<Box<dyn MandelbrotEngine>> = vec!(
+            Box::new(SimpleMandelbrotEngine::new(true)),
+            Box::new(super::super::recursive_mandelbrot_engine::RecursiveMandelbrotEngine::new(true, 64)),
+        );
+        let region = Region::new_for_center(Complex64::new(-0.75, 0.1), 0.5);
+
+        for engine in engines {
+            for &(width, height) in &[(0, 10), (10, 0)] {
+                // act
+                let (pixels, _) = Mandelbrot::new(region, width, height, 100).calculate(&*engine);
+
+                // assert
+                assert!(pixels.is_empty());
+            }
+        }
+    }
 }
@@ -1,17 +1,30 @@
 use super::mandelbrot::Mandelbrot;
+use super::render_stats::RenderStats;
+use super::window::Window;
+use super::pixel_band::PixelBand;
 
 
-pub trait MandelbrotEngine {
-    fn calculate(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>) {
+pub trait MandelbrotEngine: Sync {
+    fn calculate(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>) -> RenderStats {
         if self.should_calculate_in_parallel() {
-            self.calculate_in_parallel(mandelbrot, pixels);
+            self.calculate_in_parallel(mandelbrot, pixels)
         } else {
-            self.calculate_serially(mandelbrot, pixels);
+            self.calculate_serially(mandelbrot, pixels)
         }
     }
 
 
+    fn name(&self) -> &'static str;
     fn should_calculate_in_parallel(&self) -> bool;
-    fn calculate_serially(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>);
-    fn calculate_in_parallel(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>);
+
+    // The height of the bands the image is split into, if the pixels depend on it
+    fn band_height(&self) -> Option<u32> {
+        None
+    }
+
+
+    fn calculate_serially(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>) -> RenderStats;
+    fn calculate_in_parallel(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>) -> RenderStats;
+    /// Calculates the escape times of the pixels inside the window, which lie in the pixel band
+    fn calculate_band(&self, mandelbrot: &Mandelbrot, window: &Window, pixel_band: &mut PixelBand) -> RenderStats;
 }
@@ -0,0 +1,169 @@
+// Misiurewicz points are the c for which 0 is strictly preperiodic: z_{k+p}(c) = z_k(c) with z_0 = 0 and
+// z_{n+1} = z_n^2 + c for the preperiod k and period p. The set spirals around them, repeating itself at the scale of
+// the cycle's multiplier.
+use std::ops::RangeInclusive;
+
+use num::complex::Complex64;
+
+use super::nucleus::{newton_guesses, without_rounding_noise};
+use super::region::Region;
+
+
+const NEWTON_STEPS: u32 = 64;
+// Relative size of the last Newton step
+const TOLERANCE: f64 = 4.0 * f64::EPSILON;
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct MisiurewiczPoint {
+    pub point: Complex64,
+    pub preperiod: u32,
+    pub period: u32,
+}
+
+
+impl MisiurewiczPoint {
+    // Newton's method for the Misiurewicz point of preperiod and period near guess
+    pub fn find(guess: Complex64, preperiod: u32, period: u32) -> Option<MisiurewiczPoint> {
+        converged_point(guess, preperiod, period, f64::INFINITY)
+            .map(|point| MisiurewiczPoint { point, preperiod, period })
+    }
+
+
+    // The point of the lowest preperiod plus period (the lower period first) in the region, Newton's method is started
+    // from a grid of points in the region
+    pub fn find_in_region(region: &Region, preperiods: RangeInclusive<u32>, periods: RangeInclusive<u32>) -> Option<MisiurewiczPoint> {
+        let guesses = newton_guesses(region);
+        // Steps larger than the region mostly run off to points outside of it, giving up early saves most of the time
+        let max_step = region.width() + region.height();
+        let mut types: Vec<(u32, u32)> = preperiods.flat_map(|preperiod| periods.clone().map(move |period| (preperiod, period))).collect();
+        types.sort_by_key(|&(preperiod, period)| (preperiod + period, period));
+
+        types.into_iter()
+            .filter_map(|(preperiod, period)| {
+                guesses.iter()
+                    .filter_map(|guess| converged_point(*guess, preperiod, period, max_step))
+                    .find(|point| region.contains(*point))
+                    .map(|point| MisiurewiczPoint { point, preperiod, period })
+            })
+            .next()
+    }
+
+
+    // Derivative of z_{k+p} with respect to z_k: the spiral repeats itself when zooming in by its modulus, rotated by
+    // its argument
+    pub fn multiplier(&self) -> Complex64 {
+        let orbit = orbit(self.point, self.preperiod + self.period);
+
+        orbit[self.preperiod as usize..(self.preperiod + self.period) as usize].iter()
+            .fold(Complex64::new(1.0, 0.0), |multiplier, &(z, _)| multiplier * 2.0 * z)
+    }
+
+
+    // Radius of the view where |z_{k+p} - z_k| grows to about 1 around the point, which shows its spiral
+    pub fn radius(&self) -> f64 {
+        let orbit = orbit(self.point, self.preperiod + self.period);
+        let derivative = orbit[(self.preperiod + self.period) as usize].1 - orbit[self.preperiod as usize].1;
+
+        1.0 / derivative.norm()
+    }
+
+
+    pub fn region(&self) -> Region {
+        Region::new_for_center(self.point, self.radius())
+    }
+}
+
+
+fn converged_point(guess: Complex64, preperiod: u32, period: u32, max_step: f64) -> Option<Complex64> {
+    if preperiod < 2 || period < 1 {
+        return None;
+    }
+
+    let mut c = guess;
+
+    for _ in 0..NEWTON_STEPS {
+        let step = newton_step(c, preperiod, period);
+        c -= step;
+
+        if !c.is_finite() || step.norm() > max_step {
+            return None;
+        }
+
+        if step.norm() <= TOLERANCE * c.norm() {
+            return Some(without_rounding_noise(c));
+        }
+    }
+
+    None
+}
+
+
+// z_n and its derivative with respect to c for n from 0 to iterations
+fn orbit(c: Complex64, iterations: u32) -> Vec<(Complex64, Complex64)> {
+    let mut z = Complex64::new(0.0, 0.0);
+    let mut dz = Complex64::new(0.0, 0.0);
+    let mut orbit = vec![(z, dz)];
+
+    for _ in 0..iterations {
+        dz = 2.0 * z * dz + 1.0;
+        z = z * z + c;
+        orbit.push((z, dz));
+    }
+
+    orbit
+}
+
+
+// Newton's method for z_{k+p} - z_k divided by z_{i+p} - z_i for all i < k, which removes the roots of lower preperiods
+// (and the nuclei of period p) that would attract it otherwise. The step is the inverse of the logarithmic derivative.
+fn newton_step(c: Complex64, preperiod: u32, period: u32) -> Complex64 {
+    let orbit = orbit(c, preperiod + period);
+
+    if orbit[(preperiod + period) as usize].0 == orbit[preperiod as usize].0 {
+        return Complex64::new(0.0, 0.0);
+    }
+
+    let log_derivative = |i: usize| {
+        let (z, dz) = orbit[i + period as usize];
+        let (earlier_z, earlier_dz) = orbit[i];
+
+        (dz - earlier_dz) / (z - earlier_z)
+    };
+
+    let divisors: Complex64 = (0..preperiod as usize).map(&log_derivative).sum();
+
+    1.0 / (log_derivative(preperiod as usize) - divisors)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_converges_to_tip_of_antenna_and_i() {
+        // act
+        let tip = MisiurewiczPoint::find(Complex64::new(-1.9, 0.1), 2, 1).unwrap();
+        let i = MisiurewiczPoint::find(Complex64::new(0.1, 0.9), 2, 2).unwrap();
+
+        // assert
+        assert!((tip.point - Complex64::new(-2.0, 0.0)).norm() < 1e-15);
+        assert!((i.point - Complex64::new(0.0, 1.0)).norm() < 1e-15);
+        assert!((i.multiplier() - Complex64::new(4.0, 4.0)).norm() < 1e-12);
+        assert_eq!(tip.region(), Region::new_for_center(tip.point, 0.125));
+    }
+
+    #[test]
+    fn find_in_region_detects_preperiod_and_period() {
+        // arrange
+        let region = Region::new_for_center(Complex64::new(0.02, 0.98), 0.1);
+
+        // act
+        let point = MisiurewiczPoint::find_in_region(&region, 2..=8, 1..=8).unwrap();
+
+        // assert
+        assert_eq!((point.preperiod, point.period), (2, 2));
+        assert!((point.point - Complex64::new(0.0, 1.0)).norm() < 1e-15);
+    }
+}
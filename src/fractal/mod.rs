@@ -4,9 +4,17 @@ pub mod mandelbrot;
 pub mod mandelbrot_engine;
 pub mod simple_mandelbrot_engine;
 pub mod recursive_mandelbrot_engine;
+pub mod render_stats;
 
-mod pixel;
-mod pixel_band;
-mod window;
-mod window_iterator;
-mod escape_time;
+// The building blocks of the engines, public for the benchmarks and the command line renderer (see lib.rs)
+pub mod pixel;
+pub mod pixel_band;
+pub mod window;
+pub mod window_iterator;
+pub mod escape_time;
+pub mod double_double;
+pub mod nucleus;
+pub mod misiurewicz;
+
+pub mod coloring;
+pub mod renderer;
@@ -0,0 +1,237 @@
+// Nuclei are the centers of the hyperbolic components (atoms) of the set: the c for which 0 is periodic, z_p(c) = 0
+// with z_0 = 0 and z_{n+1} = z_n^2 + c. Every minibrot has the nucleus of its main cardioid at its center.
+use num::complex::Complex64;
+
+use super::double_double::DoubleDoubleComplex;
+use super::region::Region;
+
+
+// Points per side of the grid of starting points for Newton's method
+const GUESSES_PER_SIDE: u32 = 7;
+const NEWTON_STEPS: u32 = 64;
+const DOUBLE_DOUBLE_NEWTON_STEPS: u32 = 16;
+// Relative size of the last Newton step
+const F64_TOLERANCE: f64 = 4.0 * f64::EPSILON;
+const DOUBLE_DOUBLE_TOLERANCE: f64 = 1e-30;
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum BoxPeriod {
+    Found(u32),
+    // The orbit of a corner escaped at this period, before the image of the corners surrounded 0
+    Escaped(u32),
+    NotFound,
+}
+
+
+// The lowest period p for which the image of the region's corners under z_p surrounds 0, which means an atom of
+// period p is likely inside the region (Munafo's box period method). Regions outside the set can give false positives.
+pub fn box_period(region: &Region, max_period: u32) -> BoxPeriod {
+    let corners = [
+        Complex64::new(region.min_re(), region.min_im()),
+        Complex64::new(region.min_re() + region.width(), region.min_im()),
+        Complex64::new(region.min_re() + region.width(), region.min_im() + region.height()),
+        Complex64::new(region.min_re(), region.min_im() + region.height()),
+    ];
+    let mut points = [Complex64::new(0.0, 0.0); 4];
+
+    for period in 1..=max_period {
+        for (point, corner) in points.iter_mut().zip(corners.iter()) {
+            *point = *point * *point + corner;
+        }
+
+        if points.iter().any(|point| !point.is_finite()) {
+            return BoxPeriod::Escaped(period);
+        }
+
+        if surrounds_origin(&points) {
+            return BoxPeriod::Found(period);
+        }
+    }
+
+    BoxPeriod::NotFound
+}
+
+
+// Even-odd rule with a ray along the positive real axis
+fn surrounds_origin(polygon: &[Complex64]) -> bool {
+    let mut inside = false;
+
+    for (i, start) in polygon.iter().enumerate() {
+        let end = polygon[(i + 1) % polygon.len()];
+
+        if (start.im > 0.0) != (end.im > 0.0) {
+            let crossing_re = start.re - start.im * (end.re - start.re) / (end.im - start.im);
+
+            if crossing_re > 0.0 {
+                inside = !inside;
+            }
+        }
+    }
+
+    inside
+}
+
+
+// The nucleus of period p nearest to the center of the region, Newton's method may run off to nuclei outside the
+// region from some starting points
+pub fn nucleus_in_region(region: &Region, period: u32, high_precision: bool) -> Option<Complex64> {
+    newton_guesses(region).into_iter()
+        .filter_map(|guess| nucleus(guess, period, high_precision))
+        .find(|nucleus| region.contains(*nucleus))
+}
+
+
+// Starting points for Newton's method in a grid over the region, nearest to the center first
+pub fn newton_guesses(region: &Region) -> Vec<Complex64> {
+    let center = Complex64::new(region.min_re() + region.width() / 2.0, region.min_im() + region.height() / 2.0);
+    let mut guesses: Vec<Complex64> = (0..GUESSES_PER_SIDE * GUESSES_PER_SIDE)
+        .map(|i| {
+            let x = (i % GUESSES_PER_SIDE) as f64 + 0.5;
+            let y = (i / GUESSES_PER_SIDE) as f64 + 0.5;
+
+            Complex64::new(region.min_re() + x * region.width() / GUESSES_PER_SIDE as f64,
+                           region.min_im() + y * region.height() / GUESSES_PER_SIDE as f64)
+        })
+        .collect();
+    guesses.sort_by(|a, b| (a - center).norm_sqr().partial_cmp(&(b - center).norm_sqr()).unwrap());
+
+    guesses
+}
+
+
+// Newton's method for z_p(c) = 0 from guess, continued in double-double precision for deep views, where f64 rounding
+// leaves the nucleus a few ulps (a visible part of the view) off or keeps the steps from converging at all
+pub fn nucleus(guess: Complex64, period: u32, high_precision: bool) -> Option<Complex64> {
+    let mut c = guess;
+
+    for _ in 0..NEWTON_STEPS {
+        let step = newton_step(c, period);
+        c -= step;
+
+        if !c.is_finite() {
+            return None;
+        }
+
+        if !high_precision && step.norm() <= F64_TOLERANCE * c.norm() {
+            return Some(without_rounding_noise(c));
+        }
+    }
+
+    if !high_precision {
+        return None;
+    }
+
+    let mut c = DoubleDoubleComplex::new(c);
+
+    for _ in 0..DOUBLE_DOUBLE_NEWTON_STEPS {
+        let step = double_double_newton_step(c, period);
+        c = c - step;
+
+        if step.to_complex64().norm() <= DOUBLE_DOUBLE_TOLERANCE * c.to_complex64().norm() {
+            return Some(without_rounding_noise(c.to_complex64()));
+        }
+    }
+
+    None
+}
+
+
+// Nuclei on the real axis end up with an imaginary part far below the precision of the real part (and vice versa)
+pub fn without_rounding_noise(c: Complex64) -> Complex64 {
+    let noise = f64::EPSILON * c.norm();
+
+    Complex64::new(if c.re.abs() < noise { 0.0 } else { c.re }, if c.im.abs() < noise { 0.0 } else { c.im })
+}
+
+
+fn newton_step(c: Complex64, period: u32) -> Complex64 {
+    let mut z = Complex64::new(0.0, 0.0);
+    let mut dz = Complex64::new(0.0, 0.0);
+
+    for _ in 0..period {
+        dz = 2.0 * z * dz + 1.0;
+        z = z * z + c;
+    }
+
+    z / dz
+}
+
+
+fn double_double_newton_step(c: DoubleDoubleComplex, period: u32) -> DoubleDoubleComplex {
+    let one = DoubleDoubleComplex::new(Complex64::new(1.0, 0.0));
+    let two = DoubleDoubleComplex::new(Complex64::new(2.0, 0.0));
+    let mut z = DoubleDoubleComplex::default();
+    let mut dz = DoubleDoubleComplex::default();
+
+    for _ in 0..period {
+        dz = two * z * dz + one;
+        z = z * z + c;
+    }
+
+    z / dz
+}
+
+
+// Estimates the size of the atom's minibrot relative to the whole set (which has size 1), the argument is the
+// rotation of the minibrot
+pub fn size(nucleus: Complex64, period: u32) -> Complex64 {
+    let mut z = Complex64::new(0.0, 0.0);
+    let mut derivative = Complex64::new(1.0, 0.0);
+    let mut sum = Complex64::new(1.0, 0.0);
+
+    for _ in 1..period {
+        z = z * z + nucleus;
+        derivative = 2.0 * z * derivative;
+        sum += 1.0 / derivative;
+    }
+
+    1.0 / (sum * derivative * derivative)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AIRSHIP_NUCLEUS: f64 = -1.7548776662466927;
+
+    #[test]
+    fn box_period_finds_lowest_period_in_region() {
+        // act & assert
+        assert_eq!(box_period(&Region::new_for_center(Complex64::new(-1.75, 0.01), 0.02), 100), BoxPeriod::Found(3));
+        assert_eq!(box_period(&Region::new_for_center(Complex64::new(-0.2, 0.0), 0.5), 100), BoxPeriod::Found(1));
+        assert_eq!(box_period(&Region::new_for_center(Complex64::new(-0.2, 0.0), 0.5), 0), BoxPeriod::NotFound);
+    }
+
+    #[test]
+    fn box_period_reports_escaped_corners() {
+        // act
+        let box_period = box_period(&Region::new_for_center(Complex64::new(10.0, 10.0), 1e-6), 100000);
+
+        // assert
+        assert!(matches!(box_period, BoxPeriod::Escaped(_)));
+    }
+
+    #[test]
+    fn nucleus_converges_to_airship() {
+        // act
+        let rounded = nucleus(Complex64::new(-1.75, 0.01), 3, false).unwrap();
+        let polished = nucleus(Complex64::new(-1.75, 0.01), 3, true).unwrap();
+
+        // assert
+        assert!((rounded - Complex64::new(AIRSHIP_NUCLEUS, 0.0)).norm() < 1e-15);
+        assert_eq!(polished, Complex64::new(AIRSHIP_NUCLEUS, 0.0));
+    }
+
+    #[test]
+    fn size_of_whole_set_is_one_and_minibrots_are_smaller() {
+        // act
+        let set_size = size(Complex64::new(0.0, 0.0), 1);
+        let airship_size = size(Complex64::new(AIRSHIP_NUCLEUS, 0.0), 3);
+
+        // assert
+        assert_eq!(set_size, Complex64::new(1.0, 0.0));
+        assert!(airship_size.norm() > 0.01 && airship_size.norm() < 0.03);
+    }
+}
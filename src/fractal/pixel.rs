@@ -1,3 +1,4 @@
+/// Position of a pixel in the image, counted from the top left corner
 #[derive(Debug)]
 pub struct Pixel {
     pub x: u32,
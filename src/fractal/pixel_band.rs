@@ -1,6 +1,7 @@
 use super::pixel::Pixel;
 
 
+/// The escape times of a band of rows, the part of the image buffer starting at the pixel index `offset`
 pub struct PixelBand<'a> {
     pixels: &'a mut [u8],
     offset: usize,
@@ -13,8 +14,9 @@ impl<'a> PixelBand<'a> {
     }
 
 
+    /// Sets the escape time of a pixel of the band, `whole_display_width` is the width of the image
     pub fn set_color_of_pixel(&mut self, color: u8, pixel: &Pixel, whole_display_width: u32) {
-        let index = (pixel.y * whole_display_width + pixel.x) as usize;
+        let index = (pixel.y as usize) * (whole_display_width as usize) + (pixel.x as usize);
         self.pixels[index - self.offset] = color;
     }
 }
@@ -50,7 +52,7 @@ mod tests {
         // act
         {
             let mut chunks: Vec<&mut [u8]> = pixels.chunks_mut(10 * 5).collect();
-            let mut pixel_band = PixelBand::new(&mut chunks[1], 10 * 5);
+            let mut pixel_band = PixelBand::new(chunks[1], 10 * 5);
             pixel_band.set_color_of_pixel(42, &pixel, 10);
         }
 
@@ -3,3 +3,5 @@ pub use fractal::mandelbrot::Mandelbrot;
 pub use fractal::mandelbrot_engine::MandelbrotEngine;
 pub use fractal::simple_mandelbrot_engine::SimpleMandelbrotEngine;
 pub use fractal::recursive_mandelbrot_engine::RecursiveMandelbrotEngine;
+pub use fractal::render_stats::{RenderStats, PhaseTiming, PhaseTimer};
+pub use fractal::renderer::{Renderer, EngineType, Image};
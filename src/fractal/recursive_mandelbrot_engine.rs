@@ -1,4 +1,6 @@
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
+#[cfg(feature = "parallel")]
 use num_cpus;
 
 use super::mandelbrot_engine::MandelbrotEngine;
@@ -8,11 +10,12 @@ use super::pixel::Pixel;
 use super::pixel_band::PixelBand;
 use super::window_iterator::WindowAreaIterator;
 use super::window_iterator::WindowBorderIterator;
-use super::escape_time::EscapeTime;
+use super::render_stats::RenderStats;
 
 
 pub struct RecursiveMandelbrotEngine {
     in_parallel: bool,
+    #[cfg_attr(not(feature = "parallel"), allow(dead_code))]
     band_height: u32,
 }
 
@@ -20,69 +23,121 @@ impl RecursiveMandelbrotEngine {
     pub fn new(in_parallel: bool, band_height: u32) -> RecursiveMandelbrotEngine {
         RecursiveMandelbrotEngine { in_parallel, band_height }
     }
+
+
+    // The last band needs at least 8 rows, so the band height is lowered until the bands fit the height of the image
+    #[cfg(feature = "parallel")]
+    fn band_height_for(&self, mandelbrot_height: u32) -> u32 {
+        (8..self.band_height.max(8) + 1).rev()
+            .find(|band_height| mandelbrot_height.is_multiple_of(*band_height) || mandelbrot_height % band_height >= 8)
+            .unwrap_or(mandelbrot_height)
+    }
 }
 
 
 impl MandelbrotEngine for RecursiveMandelbrotEngine {
+    fn name(&self) -> &'static str {
+        "Recursive"
+    }
+
+
     fn should_calculate_in_parallel(&self) -> bool {
         self.in_parallel
     }
 
 
-    fn calculate_serially(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>) {
+    // Bands are calculated separately in parallel, so their borders are calculated even inside uniform areas
+    fn band_height(&self) -> Option<u32> {
+        if self.in_parallel { Some(self.band_height) } else { None }
+    }
+
+
+    fn calculate_serially(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>) -> RenderStats {
         let window = Window::new(0, 0, mandelbrot.width, mandelbrot.height);
         let mut pixel_band = PixelBand::new(pixels, 0);
+        let mut stats = RenderStats::new();
 
-        calculate_recursive(mandelbrot, &window, &mut pixel_band);
+        calculate_recursive(mandelbrot, &window, &mut pixel_band, &mut stats);
+
+        stats
     }
 
 
-    fn calculate_in_parallel(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>) {
-        verify_band_height(self.band_height, mandelbrot.height);
+    #[cfg(feature = "parallel")]
+    fn calculate_in_parallel(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>) -> RenderStats {
+        if mandelbrot.width == 0 || mandelbrot.height == 0 {
+            return RenderStats::new();
+        }
+
+        let band_height = self.band_height_for(mandelbrot.height);
+        verify_band_height(band_height, mandelbrot.height);
 
-        let band_width = (mandelbrot.width * self.band_height) as usize;
+        let band_width = mandelbrot.width as usize * band_height as usize;
         let workload: Vec<(PixelBand, Window)> = pixels
             .chunks_mut(band_width)
             .enumerate()
             .map(|(i, pixel_chunk)| {
                 let current_band_height = pixel_chunk.len() as u32 / mandelbrot.width;
+                let window = ith_window(i, mandelbrot.width, band_height, current_band_height);
+
+                info!(event = "band_scheduled", band = i, min_y = window.min_y, height = window.height;
+                       "scheduled band {}", i);
 
-                (
-                    ith_pixel_band(i, pixel_chunk, band_width),
-                    ith_window(i, mandelbrot.width, self.band_height, current_band_height),
-                )
+                (ith_pixel_band(i, pixel_chunk, band_width), window)
             })
             .collect();
 
         workload.into_par_iter()
-            .for_each(|(mut pixel_band, window)| {
-                calculate_recursive(mandelbrot, &window, &mut pixel_band);
-            });
+            .map(|(mut pixel_band, window)| {
+                let mut stats = RenderStats::new();
+                calculate_recursive(mandelbrot, &window, &mut pixel_band, &mut stats);
+
+                stats
+            })
+            .reduce(RenderStats::new, RenderStats::merged)
+    }
+
+
+    #[cfg(not(feature = "parallel"))]
+    fn calculate_in_parallel(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>) -> RenderStats {
+        self.calculate_serially(mandelbrot, pixels)
+    }
+
+
+    fn calculate_band(&self, mandelbrot: &Mandelbrot, window: &Window, pixel_band: &mut PixelBand) -> RenderStats {
+        let mut stats = RenderStats::new();
+        calculate_recursive(mandelbrot, window, pixel_band, &mut stats);
+
+        stats
     }
 }
 
 
+#[cfg(feature = "parallel")]
 fn verify_band_height(band_height: u32, mandelbrot_height: u32) {
     let number_of_bands = ((mandelbrot_height as f64) / (band_height as f64)).ceil() as u32;
     assert!(number_of_bands > 0);
 
     let last_band_height = mandelbrot_height - (number_of_bands - 1) * band_height;
-    assert!(last_band_height >= 8);
+    assert!(last_band_height >= 8 || number_of_bands == 1);
 
     let number_of_cpus = num_cpus::get();
     if number_of_bands < (number_of_cpus as u32) {
-        println!("Notice: Performance could be increased because number of bands ({}) is lower than number of cpus ({})", number_of_bands, number_of_cpus);
+        debug!("performance could be increased because number of bands ({}) is lower than number of cpus ({})", number_of_bands, number_of_cpus);
     }
 
-    println!("Using {} bands with height {}", number_of_bands, band_height);
+    info!(event = "bands", bands = number_of_bands, band_height = band_height;
+          "using {} bands with height {}", number_of_bands, band_height);
 }
 
 
-fn ith_pixel_band(i: usize, pixel_chunk: &mut [u8], chunk_size: usize) -> PixelBand {
+#[cfg(feature = "parallel")]
+fn ith_pixel_band(i: usize, pixel_chunk: &mut [u8], chunk_size: usize) -> PixelBand<'_> {
     PixelBand::new(pixel_chunk, i * chunk_size)
 }
 
 
+#[cfg(feature = "parallel")]
 fn ith_window(i: usize, width: u32, band_height: u32, current_band_height: u32) -> Window {
     Window::new(
         0,
@@ -93,36 +148,38 @@ fn ith_window(i: usize, width: u32, band_height: u32, current_band_height: u32)
 }
 
 
-fn calculate_recursive(mandelbrot: &Mandelbrot, window: &Window, pixel_band: &mut PixelBand) {
-    let unique_escape = unique_escape_for(mandelbrot, window);
+fn calculate_recursive(mandelbrot: &Mandelbrot, window: &Window, pixel_band: &mut PixelBand, stats: &mut RenderStats) {
+    let unique_escape = unique_escape_for(mandelbrot, window, stats);
 
     if let Some(escape) = unique_escape {
         fill_window(mandelbrot, window, escape, pixel_band);
+        stats.windows_filled += 1;
         return;
     }
 
     let (part1, optional_part2) = window.split_if_sensible();
 
     if let Some(part2) = optional_part2 {
-        calculate_recursive(mandelbrot, &part1, pixel_band);
-        calculate_recursive(mandelbrot, &part2, pixel_band);
+        calculate_recursive(mandelbrot, &part1, pixel_band, stats);
+        calculate_recursive(mandelbrot, &part2, pixel_band, stats);
         return;
     }
 
-    calculate_window(mandelbrot, window, pixel_band);
+    calculate_window(mandelbrot, window, pixel_band, stats);
+    stats.windows_calculated += 1;
 }
 
 
-fn unique_escape_for(mandelbrot: &Mandelbrot, window: &Window) -> Option<u8> {
+fn unique_escape_for(mandelbrot: &Mandelbrot, window: &Window, stats: &mut RenderStats) -> Option<u8> {
     let first_pixel = Pixel { x: window.min_x, y: window.min_y };
-    let first_point = mandelbrot.point_for_pixel(&first_pixel);
-    let unique_escape = first_point.escape_time(mandelbrot.max_iterations);
+    let unique_escape = mandelbrot.escape_time_for_pixel(&first_pixel);
+    stats.record_escape_time(unique_escape, mandelbrot.max_iterations);
 
     let window_border_pixels = WindowBorderIterator::new(window);
 
     for pixel in window_border_pixels {
-        let point = mandelbrot.point_for_pixel(&pixel);
-        let escape = point.escape_time(mandelbrot.max_iterations);
+        let escape = mandelbrot.escape_time_for_pixel(&pixel);
+        stats.record_escape_time(escape, mandelbrot.max_iterations);
 
         if escape != unique_escape {
             return None;
@@ -133,7 +190,9 @@ fn unique_escape_for(mandelbrot: &Mandelbrot, window: &Window) -> Option<u8> {
 }
 
 
-fn fill_window(mandelbrot: &Mandelbrot, window: &Window, color: u8, pixel_band: &mut PixelBand) {
+// Only public for the benchmarks
+#[doc(hidden)]
+pub fn fill_window(mandelbrot: &Mandelbrot, window: &Window, color: u8, pixel_band: &mut PixelBand) {
     let window_area_pixels = WindowAreaIterator::new(window);
 
     for pixel in window_area_pixels {
@@ -142,12 +201,12 @@ fn fill_window(mandelbrot: &Mandelbrot, window: &Window, color: u8, pixel_band:
 }
 
 
-fn calculate_window(mandelbrot: &Mandelbrot, window: &Window, pixel_band: &mut PixelBand) {
+fn calculate_window(mandelbrot: &Mandelbrot, window: &Window, pixel_band: &mut PixelBand, stats: &mut RenderStats) {
     let window_area_pixels = WindowAreaIterator::new(window);
 
     for pixel in window_area_pixels {
-        let point = mandelbrot.point_for_pixel(&pixel);
-        let color = point.escape_time(mandelbrot.max_iterations);
+        let color = mandelbrot.escape_time_for_pixel(&pixel);
+        stats.record_escape_time(color, mandelbrot.max_iterations);
 
         pixel_band.set_color_of_pixel(color, &pixel, mandelbrot.width);
     }
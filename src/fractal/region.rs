@@ -1,10 +1,58 @@
 use num::complex::Complex64;
 
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub enum RegionType {
     Default,
     SeaHorseValley,
+    ElephantValley,
+    TripleSpiralValley,
+    ScepterValley,
+    MiniMandelbrot,
+    SeaHorseSpiral,
+}
+
+
+impl RegionType {
+    pub fn all() -> &'static [RegionType] {
+        &[
+            RegionType::Default,
+            RegionType::SeaHorseValley,
+            RegionType::ElephantValley,
+            RegionType::TripleSpiralValley,
+            RegionType::ScepterValley,
+            RegionType::MiniMandelbrot,
+            RegionType::SeaHorseSpiral,
+        ]
+    }
+
+    pub fn for_name(name: &str) -> Option<RegionType> {
+        RegionType::all().iter().cloned().find(|region_type| region_type.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            RegionType::Default => "Default",
+            RegionType::SeaHorseValley => "SeaHorseValley",
+            RegionType::ElephantValley => "ElephantValley",
+            RegionType::TripleSpiralValley => "TripleSpiralValley",
+            RegionType::ScepterValley => "ScepterValley",
+            RegionType::MiniMandelbrot => "MiniMandelbrot",
+            RegionType::SeaHorseSpiral => "SeaHorseSpiral",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match *self {
+            RegionType::Default => "the whole set",
+            RegionType::SeaHorseValley => "valley between the main cardioid and the period 2 bulb",
+            RegionType::ElephantValley => "valley at the cusp of the main cardioid",
+            RegionType::TripleSpiralValley => "spirals in the valley between the main cardioid and the period 3 bulb",
+            RegionType::ScepterValley => "valley between the period 4 and period 8 bulbs",
+            RegionType::MiniMandelbrot => "the largest copy of the set on the real axis",
+            RegionType::SeaHorseSpiral => "deep in a sea horse tail",
+        }
+    }
 }
 
 
@@ -33,10 +81,19 @@ impl Region {
         )
     }
 
+    pub fn new_for_corner(corner: Complex64, width: f64, height: f64) -> Region {
+        Region { min_re: corner.re, min_im: corner.im, width, height }
+    }
+
     pub fn new_for_type(region_type: RegionType) -> Region {
         match region_type {
             RegionType::Default => Region::new(-2.0, 1.0, -1.0, 1.0),
-            RegionType::SeaHorseValley => Region::new_for_center(Complex64::new(-0.74548, 0.11669), 0.01276)
+            RegionType::SeaHorseValley => Region::new_for_center(Complex64::new(-0.74548, 0.11669), 0.01276),
+            RegionType::ElephantValley => Region::new_for_center(Complex64::new(0.2925, 0.0149), 0.012),
+            RegionType::TripleSpiralValley => Region::new_for_center(Complex64::new(-0.088, 0.654), 0.0012),
+            RegionType::ScepterValley => Region::new_for_center(Complex64::new(-1.36, 0.0), 0.02),
+            RegionType::MiniMandelbrot => Region::new_for_center(Complex64::new(-1.7549, 0.0), 0.03),
+            RegionType::SeaHorseSpiral => Region::new_for_center(Complex64::new(-0.743643887037151, 0.13182590420533), 0.00003),
         }
     }
 
@@ -55,6 +112,10 @@ impl Region {
     pub fn height(&self) -> f64 {
         self.height
     }
+
+    pub fn contains(&self, point: Complex64) -> bool {
+        point.re >= self.min_re && point.re <= self.min_re + self.width && point.im >= self.min_im && point.im <= self.min_im + self.height
+    }
 }
 
 
@@ -70,7 +131,36 @@ mod tests {
         // assert
         assert_eq!(region.min_re, -2.0);
         assert_eq!(region.min_im, -1.0);
-        assert_eq!(region.region_width, 4.0);
-        assert_eq!(region.region_height, 2.0);
+        assert_eq!(region.width, 4.0);
+        assert_eq!(region.height, 2.0);
+    }
+
+    #[test]
+    fn contains_includes_border() {
+        // arrange
+        let region = Region::new(-2.0, 2.0, -1.0, 1.0);
+
+        // act & assert
+        assert!(region.contains(Complex64::new(0.0, 0.0)));
+        assert!(region.contains(Complex64::new(2.0, -1.0)));
+        assert!(!region.contains(Complex64::new(2.5, 0.0)));
+    }
+
+    #[test]
+    fn new_for_corner_returns_region() {
+        // act
+        let region = Region::new_for_corner(Complex64::new(-2.0, -1.0), 4.0, 2.0);
+
+        // assert
+        assert_eq!(region, Region::new(-2.0, 2.0, -1.0, 1.0));
+    }
+
+    #[test]
+    fn for_name_finds_every_region_type_by_its_name() {
+        // act & assert
+        for &region_type in RegionType::all() {
+            assert_eq!(RegionType::for_name(region_type.name()), Some(region_type));
+        }
+        assert_eq!(RegionType::for_name("Atlantis"), None);
     }
 }
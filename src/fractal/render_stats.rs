@@ -0,0 +1,208 @@
+use std::time::Instant;
+
+#[cfg(unix)]
+use libc;
+
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct RenderStats {
+    pub escape_time_calls: u64,
+    pub iterations: u64,
+    pub pixels_inside: u64,
+    pub pixels_outside: u64,
+    pub escape_histogram: Vec<u64>,
+    pub windows_filled: u64,
+    pub windows_calculated: u64,
+    pub phases: Vec<PhaseTiming>,
+}
+
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseTiming {
+    pub name: String,
+    pub wall_ms: f64,
+    pub cpu_ms: Option<f64>,
+}
+
+
+pub struct PhaseTimer {
+    name: String,
+    wall_start: Option<Instant>,
+    cpu_start: Option<f64>,
+}
+
+
+impl RenderStats {
+    pub fn new() -> RenderStats {
+        RenderStats::default()
+    }
+
+
+    pub fn record_escape_time(&mut self, escape: u8, max_iterations: u8) {
+        self.escape_time_calls += 1;
+
+        // The escape time loop stops one step early for points which never escape
+        self.iterations += if escape >= max_iterations {
+            u64::from(max_iterations.saturating_sub(1))
+        } else {
+            u64::from(escape)
+        };
+    }
+
+
+    pub fn record_pixels(&mut self, pixels: &[u8], max_iterations: u8) {
+        let histogram_length = max_iterations as usize + 1;
+        if self.escape_histogram.len() < histogram_length {
+            self.escape_histogram.resize(histogram_length, 0);
+        }
+
+        let mut pixels_inside = 0;
+
+        for &escape in pixels {
+            self.escape_histogram[escape as usize] += 1;
+
+            if escape == max_iterations {
+                pixels_inside += 1;
+            }
+        }
+
+        self.pixels_inside += pixels_inside;
+        self.pixels_outside += pixels.len() as u64 - pixels_inside;
+    }
+
+
+    pub fn add(&mut self, other: &RenderStats) {
+        self.escape_time_calls += other.escape_time_calls;
+        self.iterations += other.iterations;
+        self.windows_filled += other.windows_filled;
+        self.windows_calculated += other.windows_calculated;
+    }
+
+
+    pub fn merged(mut self, other: RenderStats) -> RenderStats {
+        self.add(&other);
+        self
+    }
+}
+
+
+impl PhaseTimer {
+    pub fn start(name: &str) -> PhaseTimer {
+        PhaseTimer {
+            name: name.to_string(),
+            wall_start: now(),
+            cpu_start: process_cpu_milliseconds(),
+        }
+    }
+
+
+    pub fn finish(self) -> PhaseTiming {
+        let wall_ms = self.wall_start.map_or(0.0, |wall_start| wall_start.elapsed().as_secs_f64() * 1000.0);
+        let cpu_ms = match (self.cpu_start, process_cpu_milliseconds()) {
+            (Some(start), Some(end)) => Some(end - start),
+            _ => None,
+        };
+
+        PhaseTiming { name: self.name, wall_ms, cpu_ms }
+    }
+}
+
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn now() -> Option<Instant> {
+    Some(Instant::now())
+}
+
+
+// There is no clock in the browser without JavaScript, so phases are not timed there
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn now() -> Option<Instant> {
+    None
+}
+
+
+#[cfg(unix)]
+fn process_cpu_milliseconds() -> Option<f64> {
+    let mut usage: libc::rusage = unsafe { ::std::mem::zeroed() };
+
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+
+    let user_ms = usage.ru_utime.tv_sec as f64 * 1000.0 + usage.ru_utime.tv_usec as f64 / 1000.0;
+    let system_ms = usage.ru_stime.tv_sec as f64 * 1000.0 + usage.ru_stime.tv_usec as f64 / 1000.0;
+
+    Some(user_ms + system_ms)
+}
+
+
+#[cfg(not(unix))]
+fn process_cpu_milliseconds() -> Option<f64> {
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_escape_time_counts_calls_and_iterations() {
+        // arrange
+        let mut stats = RenderStats::new();
+
+        // act
+        stats.record_escape_time(3, 200);
+        stats.record_escape_time(200, 200);
+
+        // assert
+        assert_eq!(stats.escape_time_calls, 2);
+        assert_eq!(stats.iterations, 3 + 199);
+    }
+
+    #[test]
+    fn record_pixels_builds_histogram() {
+        // arrange
+        let mut stats = RenderStats::new();
+        let pixels: Vec<u8> = vec!(1, 1, 2, 4, 4, 4);
+
+        // act
+        stats.record_pixels(&pixels, 4);
+
+        // assert
+        assert_eq!(stats.escape_histogram, vec!(0, 2, 1, 0, 3));
+        assert_eq!(stats.pixels_inside, 3);
+        assert_eq!(stats.pixels_outside, 3);
+    }
+
+    #[test]
+    fn record_pixels_accumulates_bands() {
+        // arrange
+        let mut stats = RenderStats::new();
+
+        // act
+        stats.record_pixels(&[1, 4], 4);
+        stats.record_pixels(&[4, 4], 4);
+
+        // assert
+        assert_eq!(stats.escape_histogram, vec!(0, 1, 0, 0, 3));
+        assert_eq!(stats.pixels_inside, 3);
+        assert_eq!(stats.pixels_outside, 1);
+    }
+
+    #[test]
+    fn merged_adds_counters() {
+        // arrange
+        let first = RenderStats { escape_time_calls: 1, iterations: 2, windows_filled: 3, windows_calculated: 4, ..RenderStats::default() };
+        let second = RenderStats { escape_time_calls: 10, iterations: 20, windows_filled: 30, windows_calculated: 40, ..RenderStats::default() };
+
+        // act
+        let stats = first.merged(second);
+
+        // assert
+        assert_eq!(stats.escape_time_calls, 11);
+        assert_eq!(stats.iterations, 22);
+        assert_eq!(stats.windows_filled, 33);
+        assert_eq!(stats.windows_calculated, 44);
+    }
+}
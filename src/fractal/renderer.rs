@@ -0,0 +1,224 @@
+use num::complex::Complex64;
+
+use super::region::{Region, RegionType};
+use super::mandelbrot::Mandelbrot;
+use super::mandelbrot_engine::MandelbrotEngine;
+use super::simple_mandelbrot_engine::SimpleMandelbrotEngine;
+use super::recursive_mandelbrot_engine::RecursiveMandelbrotEngine;
+use super::render_stats::RenderStats;
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum EngineType {
+    Simple,
+    Recursive,
+}
+
+
+impl EngineType {
+    pub fn all() -> &'static [EngineType] {
+        &[EngineType::Simple, EngineType::Recursive]
+    }
+
+    // The names used on the command line
+    pub fn for_name(name: &str) -> Option<EngineType> {
+        EngineType::all().iter().cloned().find(|engine_type| engine_type.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            EngineType::Simple => "Default",
+            EngineType::Recursive => "Recursive",
+        }
+    }
+
+    // The band height is only used by the recursive engine
+    pub fn engine(&self, in_parallel: bool, band_height: u32) -> Box<dyn MandelbrotEngine> {
+        match *self {
+            EngineType::Simple => Box::new(SimpleMandelbrotEngine::new(in_parallel)),
+            EngineType::Recursive => Box::new(RecursiveMandelbrotEngine::new(in_parallel, band_height)),
+        }
+    }
+}
+
+
+// Renders images without wiring up regions and engines by hand, i.e.
+// Renderer::new().region_type(RegionType::SeaHorseValley).size(1920, 1080).parallel(true).render()
+#[derive(Debug, PartialEq, Clone)]
+pub struct Renderer {
+    region: Region,
+    width: u32,
+    height: u32,
+    max_iterations: u8,
+    engine_type: EngineType,
+    in_parallel: bool,
+    band_height: u32,
+    rotation: f64,
+    julia_constant: Option<Complex64>,
+}
+
+
+// Escape times of the pixels row by row, max iterations for points inside the set
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    pub stats: RenderStats,
+}
+
+
+impl Default for Renderer {
+    fn default() -> Renderer {
+        Renderer {
+            region: Region::new_for_type(RegionType::Default),
+            width: 1024,
+            height: 768,
+            max_iterations: 255,
+            engine_type: EngineType::Simple,
+            in_parallel: false,
+            band_height: 64,
+            rotation: 0.0,
+            julia_constant: None,
+        }
+    }
+}
+
+
+impl Renderer {
+    pub fn new() -> Renderer {
+        Renderer::default()
+    }
+
+
+    pub fn region(self, region: Region) -> Renderer {
+        Renderer { region, ..self }
+    }
+
+
+    pub fn region_type(self, region_type: RegionType) -> Renderer {
+        self.region(Region::new_for_type(region_type))
+    }
+
+
+    pub fn center(self, center: Complex64, radius: f64) -> Renderer {
+        self.region(Region::new_for_center(center, radius))
+    }
+
+
+    pub fn size(self, width: u32, height: u32) -> Renderer {
+        Renderer { width, height, ..self }
+    }
+
+
+    pub fn max_iterations(self, max_iterations: u8) -> Renderer {
+        Renderer { max_iterations, ..self }
+    }
+
+
+    pub fn engine_type(self, engine_type: EngineType) -> Renderer {
+        Renderer { engine_type, ..self }
+    }
+
+
+    pub fn parallel(self, in_parallel: bool) -> Renderer {
+        Renderer { in_parallel, ..self }
+    }
+
+
+    pub fn band_height(self, band_height: u32) -> Renderer {
+        Renderer { band_height, ..self }
+    }
+
+
+    // In radians
+    pub fn rotation(self, rotation: f64) -> Renderer {
+        Renderer { rotation, ..self }
+    }
+
+
+    pub fn julia_constant(self, julia_constant: Complex64) -> Renderer {
+        Renderer { julia_constant: Some(julia_constant), ..self }
+    }
+
+
+    pub fn mandelbrot(&self) -> Mandelbrot {
+        let mandelbrot = Mandelbrot::new(self.region, self.width, self.height, self.max_iterations).with_rotation(self.rotation);
+
+        match self.julia_constant {
+            Some(julia_constant) => mandelbrot.with_julia_constant(julia_constant),
+            None => mandelbrot,
+        }
+    }
+
+
+    pub fn engine(&self) -> Box<dyn MandelbrotEngine> {
+        self.engine_type.engine(self.in_parallel, self.band_height)
+    }
+
+
+    pub fn render(&self) -> Image {
+        let (pixels, stats) = self.mandelbrot().calculate(&*self.engine());
+
+        Image { width: self.width, height: self.height, pixels, stats }
+    }
+
+
+    // Hands the image band by band (of band height rows) to the consumer, see Mandelbrot::calculate_bands
+    pub fn render_bands<F, E>(&self, consume_band: F) -> Result<RenderStats, E>
+        where F: FnMut(&[u8]) -> Result<(), E>
+    {
+        self.mandelbrot().calculate_bands(&*self.engine(), self.band_height, consume_band)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_matches_mandelbrot_calculated_with_engine() {
+        // arrange
+        let renderer = Renderer::new()
+            .region_type(RegionType::SeaHorseValley)
+            .size(48, 32)
+            .max_iterations(100)
+            .engine_type(EngineType::Recursive)
+            .band_height(8);
+        let mandelbrot = Mandelbrot::new(Region::new_for_type(RegionType::SeaHorseValley), 48, 32, 100);
+
+        // act
+        let image = renderer.render();
+
+        // assert
+        assert_eq!((image.width, image.height), (48, 32));
+        assert_eq!(image.pixels, mandelbrot.calculate(&SimpleMandelbrotEngine::new(false)).0);
+    }
+
+    #[test]
+    fn render_in_parallel_lowers_band_height_for_short_last_band() {
+        // arrange
+        // 70 rows in bands of 64 would leave 6 rows for the last band
+        let renderer = Renderer::new()
+            .size(100, 70)
+            .max_iterations(100)
+            .engine_type(EngineType::Recursive)
+            .parallel(true);
+        let mandelbrot = Mandelbrot::new(Region::new_for_type(RegionType::Default), 100, 70, 100);
+
+        // act
+        let image = renderer.render();
+
+        // assert
+        assert_eq!(image.pixels, mandelbrot.calculate(&SimpleMandelbrotEngine::new(false)).0);
+    }
+
+    #[test]
+    fn engine_type_for_name_uses_command_line_names() {
+        // act & assert
+        assert_eq!(EngineType::for_name("Default"), Some(EngineType::Simple));
+        assert_eq!(EngineType::for_name("Recursive"), Some(EngineType::Recursive));
+        assert_eq!(EngineType::for_name("Simple"), None);
+    }
+}
@@ -1,12 +1,15 @@
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 use super::mandelbrot_engine::MandelbrotEngine;
 use super::mandelbrot::Mandelbrot;
 use super::window::Window;
-use super::window_iterator::{WindowLineIterator, WindowAreaIterator};
-use super::escape_time::EscapeTime;
+use super::window_iterator::WindowAreaIterator;
+#[cfg(feature = "parallel")]
+use super::window_iterator::WindowLineIterator;
 use super::pixel::Pixel;
 use super::pixel_band::PixelBand;
+use super::render_stats::RenderStats;
 
 
 pub struct SimpleMandelbrotEngine {
@@ -21,21 +24,31 @@ impl SimpleMandelbrotEngine {
 
 
 impl MandelbrotEngine for SimpleMandelbrotEngine {
+    fn name(&self) -> &'static str {
+        "Default"
+    }
+
+
     fn should_calculate_in_parallel(&self) -> bool {
         self.in_parallel
     }
 
 
-    fn calculate_serially(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>) {
+    fn calculate_serially(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>) -> RenderStats {
         let window = Window::new(0, 0, mandelbrot.width, mandelbrot.height);
         let pixel_iterator = WindowAreaIterator::new(&window);
 
         let mut pixel_band = PixelBand::new(pixels, 0);
-        calculate_for_pixel_iterator(mandelbrot, pixel_iterator, &mut pixel_band);
+        calculate_for_pixel_iterator(mandelbrot, pixel_iterator, &mut pixel_band)
     }
 
 
-    fn calculate_in_parallel(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>) {
+    #[cfg(feature = "parallel")]
+    fn calculate_in_parallel(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>) -> RenderStats {
+        if mandelbrot.width == 0 || mandelbrot.height == 0 {
+            return RenderStats::new();
+        }
+
         let window = Window::new(0, 0, mandelbrot.width, mandelbrot.height);
 
         let chunk_size = mandelbrot.width as usize;
@@ -43,6 +56,8 @@ impl MandelbrotEngine for SimpleMandelbrotEngine {
             .chunks_mut(chunk_size)
             .enumerate()
             .map(|(i, pixel_chunk)| {
+                trace!(event = "band_scheduled", band = i, min_y = i, height = 1; "scheduled line {}", i);
+
                 (
                     ith_pixel_band(i, pixel_chunk, chunk_size),
                     ith_pixel_iterator(i, &window),
@@ -51,30 +66,50 @@ impl MandelbrotEngine for SimpleMandelbrotEngine {
             .collect();
 
         workload.into_par_iter()
-            .for_each(|(mut pixel_band, pixel_iterator)| {
-                calculate_for_pixel_iterator(mandelbrot, pixel_iterator, &mut pixel_band);
-            });
+            .map(|(mut pixel_band, pixel_iterator)| {
+                calculate_for_pixel_iterator(mandelbrot, pixel_iterator, &mut pixel_band)
+            })
+            .reduce(RenderStats::new, RenderStats::merged)
+    }
+
+
+    #[cfg(not(feature = "parallel"))]
+    fn calculate_in_parallel(&self, mandelbrot: &Mandelbrot, pixels: &mut Vec<u8>) -> RenderStats {
+        self.calculate_serially(mandelbrot, pixels)
+    }
+
+
+    fn calculate_band(&self, mandelbrot: &Mandelbrot, window: &Window, pixel_band: &mut PixelBand) -> RenderStats {
+        let pixel_iterator = WindowAreaIterator::new(window);
+
+        calculate_for_pixel_iterator(mandelbrot, pixel_iterator, pixel_band)
     }
 }
 
 
-fn ith_pixel_band(i: usize, pixel_chunk: &mut [u8], chunk_size: usize) -> PixelBand {
+#[cfg(feature = "parallel")]
+fn ith_pixel_band(i: usize, pixel_chunk: &mut [u8], chunk_size: usize) -> PixelBand<'_> {
     PixelBand::new(pixel_chunk, i * chunk_size)
 }
 
 
+#[cfg(feature = "parallel")]
 fn ith_pixel_iterator(i: usize, window: &Window) -> WindowLineIterator {
     WindowLineIterator::new(window, i as u32)
 }
 
 
-fn calculate_for_pixel_iterator<I>(mandelbrot: &Mandelbrot, pixel_iterator: I, pixel_band: &mut PixelBand)
+fn calculate_for_pixel_iterator<I>(mandelbrot: &Mandelbrot, pixel_iterator: I, pixel_band: &mut PixelBand) -> RenderStats
     where I: Iterator<Item=Pixel>
 {
+    let mut stats = RenderStats::new();
+
     for pixel in pixel_iterator {
-        let point = mandelbrot.point_for_pixel(&pixel);
-        let color = point.escape_time(mandelbrot.max_iterations);
+        let color = mandelbrot.escape_time_for_pixel(&pixel);
+        stats.record_escape_time(color, mandelbrot.max_iterations);
 
         pixel_band.set_color_of_pixel(color, &pixel, mandelbrot.width);
     }
+
+    stats
 }
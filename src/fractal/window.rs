@@ -1,6 +1,7 @@
 const MINIMUM_WINDOW_SIZE: u32 = 32;
 
 
+/// A rectangle of pixels of the image, e.g. the band an engine calculates in `MandelbrotEngine::calculate_band`
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Window {
     pub min_x: u32,
@@ -15,6 +16,7 @@ impl Window {
         Window { min_x, min_y, width, height }
     }
 
+    /// Halves the longer side of windows larger than 32 pixels, smaller windows are returned as they are
     pub fn split_if_sensible(&self) -> (Window, Option<Window>) {
         // Splitting horizontally?
         if self.width > MINIMUM_WINDOW_SIZE && self.width >= self.height {
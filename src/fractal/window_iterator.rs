@@ -13,7 +13,7 @@ impl WindowLineIterator {
     pub fn new(window: &Window, fixed_y: u32) -> Self {
         WindowLineIterator {
             max_x: window.min_x + window.width,
-            fixed_y: fixed_y,
+            fixed_y,
             current_x: window.min_x,
         }
     }
@@ -41,7 +41,8 @@ pub struct WindowAreaIterator {
     window: Window,
     current_x: u32,
     current_y: u32,
-    pixels_left: u32,
+    // Above 4 gigapixels the number of pixels doesn't fit into u32
+    pixels_left: u64,
 }
 
 
@@ -51,7 +52,7 @@ impl WindowAreaIterator {
             window: *window,
             current_x: window.min_x,
             current_y: window.min_y,
-            pixels_left: window.width * window.height,
+            pixels_left: (window.width as u64) * (window.height as u64),
         }
     }
 }
@@ -69,7 +70,7 @@ impl Iterator for WindowAreaIterator {
 
         self.pixels_left -= 1;
 
-        if self.pixels_left % self.window.width == 0 {
+        if self.pixels_left.is_multiple_of(self.window.width as u64) {
             self.current_x = self.window.min_x;
             self.current_y += 1;
         } else {
@@ -139,36 +140,66 @@ impl Iterator for WindowBorderIterator {
 mod tests {
     use super::*;
 
+    fn coordinates<I>(iterator: I) -> Vec<(u32, u32)>
+        where I: Iterator<Item=Pixel>
+    {
+        iterator.map(|pixel| (pixel.x, pixel.y)).collect()
+    }
+
     #[test]
     fn window_line_iterator() {
+        // arrange
         let window = Window::new(10, 20, 6, 4);
-        let iterator = WindowLineIterator::new(&window, 21);
 
-        // TODO: replace with proper assertion
-//        for (idx, pixel) in iterator.enumerate() {
-//            println!("{}: {:?}", idx, pixel);
-//        }
+        // act
+        let pixels = coordinates(WindowLineIterator::new(&window, 21));
+
+        // assert
+        assert_eq!(pixels, vec!((10, 21), (11, 21), (12, 21), (13, 21), (14, 21), (15, 21)));
     }
 
     #[test]
     fn window_area_iterator() {
+        // arrange
         let window = Window::new(10, 20, 6, 4);
-        let iterator = WindowAreaIterator::new(&window);
 
-        // TODO: replace with proper assertion
-//        for (idx, pixel) in iterator.enumerate() {
-//            println!("{}: {:?}", idx, pixel);
-//        }
+        // act
+        let pixels = coordinates(WindowAreaIterator::new(&window));
+
+        // assert
+        let expected: Vec<(u32, u32)> = (20..24)
+            .flat_map(|y| (10..16).map(move |x| (x, y)))
+            .collect();
+        assert_eq!(pixels, expected);
+    }
+
+    #[test]
+    fn window_area_iterator_for_more_than_four_gigapixels() {
+        // arrange
+        let window = Window::new(0, 0, 65536, 65537);
+
+        // act
+        let pixels = coordinates(WindowAreaIterator::new(&window).skip(65535).take(2));
+
+        // assert
+        assert_eq!(pixels, vec!((65535, 0), (0, 1)));
     }
 
     #[test]
     fn window_border_iterator() {
+        // arrange
         let window = Window::new(10, 20, 6, 4);
-        let iterator = WindowBorderIterator::new(&window);
 
-        // TODO: replace with proper assertion
-//        for (idx, pixel) in iterator.enumerate() {
-//            println!("{}: {:?}", idx, pixel);
-//        }
+        // act
+        let mut pixels = coordinates(WindowBorderIterator::new(&window));
+
+        // assert
+        let mut expected: Vec<(u32, u32)> = (20..24)
+            .flat_map(|y| (10..16).map(move |x| (x, y)))
+            .filter(|&(x, y)| x == 10 || x == 15 || y == 20 || y == 23)
+            .collect();
+        pixels.sort();
+        expected.sort();
+        assert_eq!(pixels, expected);
     }
 }
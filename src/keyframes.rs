@@ -0,0 +1,436 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use num::complex::Complex64;
+use serde_json;
+use toml;
+
+use errors::*;
+use config::Config;
+use fractal::coloring;
+use fractal::prelude::*;
+use animate::{frame_output, frame_output_arguments, parsed_frame_parallelism, render_frames};
+use render_parameters::{RenderParameters, KEYWORD_PREFIX};
+use super::parsed_resolution;
+
+
+const DEFAULT_MAX_ITERATIONS: u8 = 255;
+
+
+// Selects one of the interpolated values of a keyframe
+type Selector = dyn Fn(&Keyframe) -> f64;
+
+
+// Curve of the values between a keyframe and the next one
+#[derive(Debug, PartialEq, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Curve {
+    Linear,
+    Cubic,
+    Log,
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct KeyframeFile {
+    #[serde(default = "default_curve")]
+    pub interpolation: Curve,
+    #[serde(rename = "keyframe", alias = "keyframes")]
+    pub keyframes: Vec<Keyframe>,
+}
+
+
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+pub struct Keyframe {
+    // in seconds
+    pub time: f64,
+    pub center: (f64, f64),
+    pub radius: f64,
+    // in degrees
+    #[serde(default)]
+    pub rotation: f64,
+    #[serde(default)]
+    pub julia: Option<(f64, f64)>,
+    #[serde(default)]
+    pub palette_offset: f64,
+    #[serde(default = "default_max_iterations")]
+    pub max_iterations: u8,
+    pub curve: Option<Curve>,
+}
+
+
+// The interpolated parameters of a single frame
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct View {
+    pub center: Complex64,
+    pub radius: f64,
+    pub rotation: f64,
+    pub julia: Option<Complex64>,
+    pub palette_offset: f64,
+    pub max_iterations: u8,
+}
+
+
+fn default_curve() -> Curve {
+    Curve::Linear
+}
+
+
+fn default_max_iterations() -> u8 {
+    DEFAULT_MAX_ITERATIONS
+}
+
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("keyframes")
+        .about("Renders the frames of an animation scripted by a keyframe file (TOML or JSON)")
+        .arg(Arg::with_name("keyframe-file")
+            .help("Keyframe file with times, centers, radii, rotations, Julia constants, palette offsets and max iterations")
+            .required(true)
+            .value_name("KEYFRAME-FILE"))
+        .arg(Arg::with_name("fps")
+            .long("fps")
+            .help("Frames per second")
+            .takes_value(true)
+            .value_name("FPS")
+            .default_value("30"))
+        .arg(Arg::with_name("resolution")
+            .short("r")
+            .long("resolution")
+            .help("Specifies the resolution of the frames (format: <width>x<height>)")
+            .value_name("RESOLUTION")
+            .default_value("640x480"))
+        .arg(Arg::with_name("band-height")
+            .short("b")
+            .long("band-height")
+            .help("Specifies number of rows per band (only sensible in case of the recursive engine)")
+            .value_name("BAND-HEIGHT")
+            .default_value("64"))
+        .arg(Arg::with_name("engine")
+            .short("e")
+            .long("engine")
+            .help("Choose engine to to use (values: Default, Recursive, default: from the config, or Default)")
+            .takes_value(true)
+            .value_name("ENGINE"))
+        .arg(Arg::with_name("frame-parallelism")
+            .long("frame-parallelism")
+            .help("Renders several frames at once or each frame in parallel (values: auto, frames, pixels)")
+            .takes_value(true)
+            .value_name("FRAME-PARALLELISM")
+            .default_value("auto"))
+        .args(&frame_output_arguments())
+}
+
+
+pub fn run(arguments: &ArgMatches, config: &Config) -> Result<()> {
+    let keyframe_file = read_keyframe_file(arguments.value_of("keyframe-file").unwrap()).chain_err(|| "reading keyframe file failed")?;
+    let fps = arguments.value_of("fps").unwrap().parse::<u32>().chain_err(|| "invalid fps")?;
+    let (width, height) = parsed_resolution(arguments.value_of("resolution").unwrap()).chain_err(|| "parsing resolution failed")?;
+    let band_height = arguments.value_of("band-height").unwrap().parse::<u32>().chain_err(|| "invalid band height")?;
+    let engine_type = arguments.value_of("engine").unwrap_or(config.engine());
+    let frame_parallelism = parsed_frame_parallelism(arguments.value_of("frame-parallelism").unwrap(), width, height).chain_err(|| "parsing frame parallelism failed")?;
+
+    if fps == 0 {
+        bail!("fps has to be positive");
+    }
+
+    let in_parallel = frame_parallelism == ::animate::FrameParallelism::Pixels;
+
+    let parameters_of_view = |view: &View| RenderParameters {
+        region: Region::new_for_center(view.center, view.radius),
+        width,
+        height,
+        max_iterations: view.max_iterations,
+        engine_type: engine_type.to_string(),
+        in_parallel,
+        band_height,
+    };
+
+    let keyframes = &keyframe_file.keyframes;
+
+    // Fail early instead of after the first batch of frames
+    parameters_of_view(&view_of(&keyframes[0])).engine().chain_err(|| "parsing engine type failed")?;
+
+    let start_time = keyframes[0].time;
+    let duration = keyframes[keyframes.len() - 1].time - start_time;
+    let frame_count = (duration * fps as f64).floor() as usize + 1;
+
+    let mut output = frame_output(arguments, width, height, frame_count, fps)?;
+
+    render_frames(frame_count, frame_parallelism, &mut output, |i| {
+        let view = view_at(keyframes, keyframe_file.interpolation, start_time + i as f64 / fps as f64);
+        let parameters = parameters_of_view(&view);
+
+        let mut mandelbrot = parameters.mandelbrot().with_rotation(view.rotation.to_radians());
+
+        if let Some(julia) = view.julia {
+            mandelbrot = mandelbrot.with_julia_constant(julia);
+        }
+
+        let (mut pixels, _) = mandelbrot.calculate(&*parameters.engine()?);
+        coloring::cycle_palette(&mut pixels, view.palette_offset, view.max_iterations);
+
+        Ok((pixels, text_chunks(&parameters, &view)))
+    })?;
+
+    output.finish()
+}
+
+
+// The render parameters of the frame and the parts of the view they cannot represent (which rerender refuses)
+fn text_chunks(parameters: &RenderParameters, view: &View) -> Vec<(String, String)> {
+    let mut chunks = parameters.to_text_chunks();
+    let mut add = |keyword: &str, text: String| chunks.push((format!("{}{}", KEYWORD_PREFIX, keyword), text));
+
+    if view.rotation != 0.0 {
+        add("rotation", view.rotation.to_string());
+    }
+
+    if let Some(julia) = view.julia {
+        add("julia-constant", format!("{}/{}", julia.re, julia.im));
+    }
+
+    if view.palette_offset != 0.0 {
+        add("palette-offset", view.palette_offset.to_string());
+    }
+
+    chunks
+}
+
+
+pub fn read_keyframe_file(filename: &str) -> Result<KeyframeFile> {
+    let mut text = String::new();
+    File::open(filename)?.read_to_string(&mut text)?;
+
+    let is_json = Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+
+    let keyframe_file: KeyframeFile = if is_json { serde_json::from_str(&text)? } else { toml::from_str(&text)? };
+
+    verify_keyframes(&keyframe_file.keyframes)?;
+
+    Ok(keyframe_file)
+}
+
+
+fn verify_keyframes(keyframes: &[Keyframe]) -> Result<()> {
+    if keyframes.is_empty() {
+        bail!("at least one keyframe is required");
+    }
+
+    if keyframes.windows(2).any(|pair| pair[1].time <= pair[0].time) {
+        bail!("keyframe times have to be increasing");
+    }
+
+    if keyframes.iter().any(|keyframe| keyframe.radius <= 0.0) {
+        bail!("keyframe radii have to be positive");
+    }
+
+    // Morphing between the Mandelbrot set and a Julia set is not defined
+    if keyframes.iter().any(|keyframe| keyframe.julia.is_some() != keyframes[0].julia.is_some()) {
+        bail!("either all or no keyframes need a Julia constant");
+    }
+
+    Ok(())
+}
+
+
+// Times before the first or after the last keyframe are clamped
+pub fn view_at(keyframes: &[Keyframe], default_curve: Curve, time: f64) -> View {
+    let last = keyframes.len() - 1;
+    let segment = keyframes.iter().rposition(|keyframe| keyframe.time <= time).unwrap_or(0).min(last.saturating_sub(1));
+
+    if last == 0 {
+        return view_of(&keyframes[0]);
+    }
+
+    let from = &keyframes[segment];
+    let to = &keyframes[segment + 1];
+    let progress = ((time - from.time) / (to.time - from.time)).clamp(0.0, 1.0);
+
+    match from.curve.unwrap_or(default_curve) {
+        Curve::Linear => {
+            let value = |select: &Selector| lerp(select(from), select(to), progress);
+
+            view_from(&value, value(&|keyframe| keyframe.radius))
+        },
+        Curve::Log => {
+            let value = |select: &Selector| lerp(select(from), select(to), progress);
+            let radius = from.radius * (to.radius / from.radius).powf(progress);
+
+            // As in a zoom, the center approaches the target in proportion to the radius
+            let remaining = if from.radius != to.radius { (radius - to.radius) / (from.radius - to.radius) } else { 1.0 - progress };
+            let center = |select: &Selector| select(to) + (select(from) - select(to)) * remaining;
+
+            View {
+                center: Complex64::new(center(&|keyframe| keyframe.center.0), center(&|keyframe| keyframe.center.1)),
+                ..view_from(&value, radius)
+            }
+        },
+        Curve::Cubic => {
+            let before = &keyframes[segment.saturating_sub(1)];
+            let after = &keyframes[(segment + 2).min(last)];
+            let value = |select: &Selector| catmull_rom(select(before), select(from), select(to), select(after), progress);
+
+            view_from(&value, value(&|keyframe| keyframe.radius.ln()).exp())
+        },
+    }
+}
+
+
+fn view_of(keyframe: &Keyframe) -> View {
+    view_from(&|select: &Selector| select(keyframe), keyframe.radius)
+}
+
+
+fn view_from(value: &dyn Fn(&Selector) -> f64, radius: f64) -> View {
+    View {
+        center: Complex64::new(value(&|keyframe| keyframe.center.0), value(&|keyframe| keyframe.center.1)),
+        radius,
+        rotation: value(&|keyframe| keyframe.rotation),
+        julia: if value(&|keyframe| keyframe.julia.map_or(0.0, |_| 1.0)) > 0.0 {
+            Some(Complex64::new(value(&|keyframe| keyframe.julia.map_or(0.0, |julia| julia.0)),
+                                value(&|keyframe| keyframe.julia.map_or(0.0, |julia| julia.1))))
+        } else {
+            None
+        },
+        palette_offset: value(&|keyframe| keyframe.palette_offset),
+        max_iterations: value(&|keyframe| keyframe.max_iterations as f64).round().clamp(1.0, 255.0) as u8,
+    }
+}
+
+
+fn lerp(from: f64, to: f64, progress: f64) -> f64 {
+    from + (to - from) * progress
+}
+
+
+// Passes through the keyframes with a continuous velocity
+fn catmull_rom(before: f64, from: f64, to: f64, after: f64, progress: f64) -> f64 {
+    let t2 = progress * progress;
+    let t3 = t2 * progress;
+
+    0.5 * (2.0 * from
+        + (to - before) * progress
+        + (2.0 * before - 5.0 * from + 4.0 * to - after) * t2
+        + (3.0 * from - before - 3.0 * to + after) * t3)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(time: f64, center: (f64, f64), radius: f64) -> Keyframe {
+        Keyframe { time, center, radius, rotation: 0.0, julia: None, palette_offset: 0.0, max_iterations: 100, curve: None }
+    }
+
+    #[test]
+    fn toml_keyframe_file_is_parsed_with_defaults() {
+        // arrange
+        let text = r#"
+            interpolation = "cubic"
+
+            [[keyframe]]
+            time = 0.0
+            center = [-0.5, 0.0]
+            radius = 1.5
+
+            [[keyframe]]
+            time = 2.0
+            center = [-0.74548, 0.11669]
+            radius = 0.01
+            rotation = 90.0
+            palette_offset = 20
+            max_iterations = 200
+            curve = "log"
+        "#;
+
+        // act
+        let keyframe_file: KeyframeFile = toml::from_str(text).unwrap();
+
+        // assert
+        assert_eq!(keyframe_file.interpolation, Curve::Cubic);
+        assert_eq!(keyframe_file.keyframes.len(), 2);
+        assert_eq!(keyframe_file.keyframes[0].max_iterations, DEFAULT_MAX_ITERATIONS);
+        assert_eq!(keyframe_file.keyframes[1].curve, Some(Curve::Log));
+        assert_eq!(keyframe_file.keyframes[1].rotation, 90.0);
+    }
+
+    #[test]
+    fn verify_keyframes_rejects_decreasing_times() {
+        // arrange
+        let keyframes = vec![keyframe(1.0, (0.0, 0.0), 1.0), keyframe(0.5, (0.0, 0.0), 1.0)];
+
+        // act & assert
+        assert!(verify_keyframes(&keyframes).is_err());
+    }
+
+    #[test]
+    fn view_at_interpolates_linearly_and_log_scaled() {
+        // arrange
+        let keyframes = vec![
+            Keyframe { palette_offset: 10.0, ..keyframe(0.0, (0.0, 0.0), 1.0) },
+            Keyframe { max_iterations: 200, ..keyframe(2.0, (1.0, -1.0), 0.01) },
+        ];
+
+        // act
+        let linear = view_at(&keyframes, Curve::Linear, 1.0);
+        let log = view_at(&keyframes, Curve::Log, 1.0);
+
+        // assert
+        assert_eq!(linear.center, Complex64::new(0.5, -0.5));
+        assert_eq!(linear.radius, 0.505);
+        assert_eq!(linear.palette_offset, 5.0);
+        assert_eq!(linear.max_iterations, 150);
+        assert!((log.radius - 0.1).abs() < 1e-12);
+        assert!(log.center.re > 0.9);
+    }
+
+    #[test]
+    fn view_at_with_cubic_curve_passes_through_keyframes() {
+        // arrange
+        let keyframes = vec![
+            keyframe(0.0, (0.0, 0.0), 1.0),
+            keyframe(1.0, (1.0, 2.0), 0.5),
+            keyframe(3.0, (2.0, 0.0), 0.1),
+        ];
+
+        // act
+        let view = view_at(&keyframes, Curve::Cubic, 1.0);
+        let clamped = view_at(&keyframes, Curve::Cubic, 5.0);
+
+        // assert
+        assert!((view.center - Complex64::new(1.0, 2.0)).norm() < 1e-12);
+        assert!((view.radius - 0.5).abs() < 1e-12);
+        assert!((clamped.radius - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn text_chunks_of_rotated_frames_are_not_re_rendered() {
+        // arrange
+        let plain = view_of(&keyframe(0.0, (-0.5, 0.0), 1.0));
+        let rotated = view_of(&Keyframe { rotation: 30.0, ..keyframe(0.0, (-0.5, 0.0), 1.0) });
+        let parameters = RenderParameters {
+            region: Region::new_for_center(plain.center, plain.radius),
+            width: 64,
+            height: 48,
+            max_iterations: plain.max_iterations,
+            engine_type: "Default".to_string(),
+            in_parallel: false,
+            band_height: 64,
+        };
+
+        // act
+        let restored = RenderParameters::from_text_chunks(&text_chunks(&parameters, &plain));
+        let rejected = RenderParameters::from_text_chunks(&text_chunks(&parameters, &rotated));
+
+        // assert
+        assert_eq!(restored.unwrap(), parameters);
+        assert!(rejected.is_err());
+    }
+}
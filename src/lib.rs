@@ -0,0 +1,38 @@
+// Rendering of the Mandelbrot set as a library, the command line renderer in main.rs is built on top of it
+// Only the parallel engines log
+#[cfg_attr(feature = "parallel", macro_use)]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+
+extern crate num;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[cfg(feature = "parallel")]
+extern crate num_cpus;
+extern crate serde;
+extern crate libc;
+// The code generated by pyo3 and wasm-bindgen refers to ::core
+#[cfg(any(feature = "python", feature = "wasm"))]
+extern crate core;
+#[cfg(feature = "python")]
+extern crate pyo3;
+#[cfg(feature = "python")]
+extern crate numpy;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+
+
+mod fractal;
+pub mod ffi;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use fractal::{prelude, region, mandelbrot, mandelbrot_engine, simple_mandelbrot_engine, recursive_mandelbrot_engine, renderer,
+                  coloring, pixel, pixel_band, window};
+pub use fractal::prelude::*;
+// Internals shared with the command line renderer and the benchmarks, they are not part of the library's interface
+#[doc(hidden)]
+pub use fractal::{render_stats, window_iterator, escape_time, nucleus, misiurewicz};
@@ -0,0 +1,138 @@
+use std::io::{self, Write};
+
+use log::{self, Level, LevelFilter, Log, Metadata, Record};
+use log::kv::{self, Key, Value, VisitSource};
+use serde_json::{self, Map, Number};
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+
+struct StderrLogger {
+    level: LevelFilter,
+    format: LogFormat,
+}
+
+
+pub fn init(verbosity: i64, format: LogFormat) -> Result<(), log::SetLoggerError> {
+    let level = level_for_verbosity(verbosity);
+
+    log::set_boxed_logger(Box::new(StderrLogger { level, format }))?;
+    log::set_max_level(level);
+
+    Ok(())
+}
+
+
+fn level_for_verbosity(verbosity: i64) -> LevelFilter {
+    match verbosity {
+        v if v <= -2 => LevelFilter::Off,
+        -1 => LevelFilter::Error,
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = match self.format {
+            LogFormat::Text => text_line(record),
+            LogFormat::Json => json_line(record),
+        };
+
+        let stderr = io::stderr();
+        let _ = writeln!(stderr.lock(), "{}", line);
+    }
+
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+
+fn text_line(record: &Record) -> String {
+    let mut fields = TextFields(String::new());
+    let _ = record.key_values().visit(&mut fields);
+
+    let prefix = match record.level() {
+        Level::Error => "error",
+        Level::Warn => "warning",
+        Level::Info => "info",
+        Level::Debug => "debug",
+        Level::Trace => "trace",
+    };
+
+    format!("{}: {}{}", prefix, record.args(), fields.0)
+}
+
+
+fn json_line(record: &Record) -> String {
+    let mut fields = JsonFields(Map::new());
+    fields.0.insert("level".to_string(), record.level().as_str().into());
+    fields.0.insert("target".to_string(), record.target().into());
+    fields.0.insert("message".to_string(), record.args().to_string().into());
+
+    let _ = record.key_values().visit(&mut fields);
+
+    serde_json::Value::Object(fields.0).to_string()
+}
+
+
+struct TextFields(String);
+
+impl<'kvs> VisitSource<'kvs> for TextFields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.push_str(&format!(" {}={}", key, value));
+
+        Ok(())
+    }
+}
+
+
+struct JsonFields(Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.insert(key.to_string(), json_value(&value));
+
+        Ok(())
+    }
+}
+
+
+fn json_value(value: &Value) -> serde_json::Value {
+    if let Some(v) = value.to_bool() {
+        return v.into();
+    }
+
+    if let Some(v) = value.to_u64() {
+        return v.into();
+    }
+
+    if let Some(v) = value.to_i64() {
+        return v.into();
+    }
+
+    if let Some(v) = value.to_f64().and_then(Number::from_f64) {
+        return serde_json::Value::Number(v);
+    }
+
+    value.to_string().into()
+}
@@ -2,12 +2,25 @@
 
 #[macro_use]
 extern crate error_chain;
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
 
 extern crate clap;
 extern crate num;
-extern crate image;
 extern crate rayon;
 extern crate num_cpus;
+extern crate serde;
+extern crate serde_json;
+extern crate libc;
+extern crate png;
+extern crate tiff;
+extern crate exr;
+extern crate toml;
+extern crate gif;
+extern crate crossterm;
+extern crate fractal;
 
 
 mod errors {
@@ -15,6 +28,13 @@ mod errors {
         foreign_links {
             Io(::std::io::Error);
             Num(::std::num::ParseFloatError);
+            Json(::serde_json::Error);
+            PngEncoding(::png::EncodingError);
+            PngDecoding(::png::DecodingError);
+            Tiff(::tiff::TiffError);
+            Exr(::exr::error::Error);
+            Toml(::toml::de::Error);
+            Gif(::gif::EncodingError);
         }
     }
 }
@@ -23,28 +43,54 @@ use errors::*;
 use errors::Result;
 
 
-mod fractal;
+mod logging;
+mod bench;
+mod render_parameters;
+mod png_file;
+mod output_format;
+mod data_export;
+mod rerender;
+mod tiles;
+mod tile_cache;
+mod serve;
+mod render_cache;
+mod animate;
+mod keyframes;
+mod exponential_map;
+mod config;
+mod batch;
+mod explore;
+mod text_art;
+mod find_minibrot;
+mod find_misiurewicz;
 
 use std::fs::File;
-use clap::{App, Arg};
-use image::png::PNGEncoder;
-use image::ColorType;
+use std::time::Instant;
+use clap::{App, AppSettings, Arg, ArgMatches};
 use num::complex::Complex64;
 use fractal::prelude::*;
+use logging::LogFormat;
+use render_parameters::RenderParameters;
+use output_format::OutputFormat;
+use data_export::DataOutput;
+use render_cache::RenderCache;
+use config::Config;
+use text_art::CharacterGrid;
+use fractal::window::Window;
 
 
 fn main() {
     let result = run();
 
     if let Err(ref e) = result {
-        println!("error: {}", e);
+        eprintln!("error: {}", e);
 
         for e in e.iter().skip(1) {
-            println!("caused by: {}", e);
+            eprintln!("caused by: {}", e);
         }
 
         if let Some(backtrace) = e.backtrace() {
-            println!("backtrace: {:?}", backtrace);
+            eprintln!("backtrace: {:?}", backtrace);
         }
 
         ::std::process::exit(1);
@@ -57,11 +103,12 @@ fn run() -> Result<()> {
         .version("0.1")
         .author("sargon@me.com")
         .about("Creates awesome fractal images")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(Arg::with_name("type")
             .short("t")
             .long("type")
             .conflicts_with("center")
-            .help("Sets a region type (values: Default, SeaHorseValley)")
+            .help("Sets a region type or preset (see: fractal presets list)")
             .takes_value(true)
             .value_name("TYPE")
             .default_value("Default"))
@@ -71,20 +118,20 @@ fn run() -> Result<()> {
             .conflicts_with("type")
             .help("Specifies the center and radius of the region (format: <real>/<imag>@radius (i.e. -0.74548/0.11669@0.01276))")
             .takes_value(true)
+            .allow_hyphen_values(true)
             .value_name("CENTER-AND-RADIUS"))
         .arg(Arg::with_name("max-iterations")
             .short("i")
             .long("max-iterations")
-            .help("Sets max iterations")
+            .help("Sets max iterations (default: from the config, or 255)")
             .takes_value(true)
-            .value_name("MAX-ITERATIONS")
-            .default_value("255"))
+            .value_name("MAX-ITERATIONS"))
         .arg(Arg::with_name("resolution")
             .short("r")
             .long("resolution")
-            .help("Specifies the resolution of the image (format: <width>x<height>)")
-            .value_name("RESOLUTION")
-            .default_value("1024x768"))
+            .help("Specifies the resolution of the image (format: <width>x<height>), in characters for text formats (default: from the config, or 1024x768, or the terminal size for text)")
+            .takes_value(true)
+            .value_name("RESOLUTION"))
         .arg(Arg::with_name("parallel")
             .short("p")
             .long("parallel")
@@ -98,50 +145,304 @@ fn run() -> Result<()> {
         .arg(Arg::with_name("engine")
             .short("e")
             .long("engine")
-            .help("Choose engine to to use (values: Default, Recursive)")
+            .help("Choose engine to to use (values: Default, Recursive, default: from the config, or Default)")
             .takes_value(true)
-            .value_name("ENGINE")
-            .default_value("Default")
-        )
+            .value_name("ENGINE"))
         .arg(Arg::with_name("output-filename")
             .short("o")
             .long("--output-filename")
-            .help("Specifies the output file name of the image")
+            .help("Specifies the output file name of the image (- writes the image to stdout)")
             .required(true)
             .takes_value(true)
+            .allow_hyphen_values(true)
             .value_name("OUTPUT-FILENAME"))
+        .arg(Arg::with_name("format")
+            .long("format")
+            .alias("output-format")
+            .help("Specifies the output format instead of deriving it from the file name extension (values: png, png16, png16-rgb, tiff, pgm, ppm, exr, ascii, ansi)")
+            .takes_value(true)
+            .value_name("FORMAT"))
+        .arg(Arg::with_name("data-out")
+            .long("data-out")
+            .help("Writes the raw iteration values to the given file (formats by extension: npy, raw/bin, csv)")
+            .takes_value(true)
+            .value_name("DATA-FILENAME"))
+        .arg(Arg::with_name("data-values")
+            .long("data-values")
+            .help("Specifies the iteration values written by --data-out (values: counts, smooth)")
+            .takes_value(true)
+            .value_name("DATA-VALUES")
+            .default_value("counts"))
+        .arg(Arg::with_name("stats")
+            .long("stats")
+            .help("Writes render statistics as JSON to the given file")
+            .takes_value(true)
+            .value_name("STATS-FILENAME"))
+        .arg(Arg::with_name("stream")
+            .long("stream")
+            .conflicts_with("data-out")
+            .help("Calculates and writes the image band by band (of band height rows) to keep memory low for huge images (formats: png, png16, png16-rgb, pgm, ppm)"))
+        .arg(Arg::with_name("cache")
+            .long("cache")
+            .conflicts_with("stream")
+            .help("Reuses the iterations of an identical earlier render from the on-disk cache, or stores them there"))
+        .arg(Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .multiple(true)
+            .global(true)
+            .conflicts_with("quiet")
+            .help("Increases the verbosity of diagnostics on stderr (may be repeated)"))
+        .arg(Arg::with_name("quiet")
+            .short("q")
+            .long("quiet")
+            .multiple(true)
+            .global(true)
+            .conflicts_with("verbose")
+            .help("Decreases the verbosity of diagnostics on stderr (may be repeated)"))
+        .arg(Arg::with_name("log-format")
+            .long("log-format")
+            .help("Specifies the format of diagnostics on stderr (values: text, json)")
+            .takes_value(true)
+            .value_name("LOG-FORMAT")
+            .default_value("text")
+            .global(true))
+        .subcommand(bench::subcommand())
+        .subcommand(rerender::subcommand())
+        .subcommand(tiles::subcommand())
+        .subcommand(serve::subcommand())
+        .subcommand(render_cache::subcommand())
+        .subcommand(animate::subcommand())
+        .subcommand(keyframes::subcommand())
+        .subcommand(exponential_map::subcommand())
+        .subcommand(config::subcommand())
+        .subcommand(batch::subcommand())
+        .subcommand(explore::subcommand())
+        .subcommand(find_minibrot::subcommand())
+        .subcommand(find_misiurewicz::subcommand())
         .get_matches();
 
-    let (width, height) = parsed_resolution(arguments.value_of("resolution").unwrap()).chain_err(|| "parsing resolution failed")?;
-    let max_iterations = parsed_max_iterations(arguments.value_of("max-iterations").unwrap()).chain_err(|| "parsing max iterations failed")?;
-    let region = parsed_region(arguments.value_of("type"), arguments.value_of("center-and-radius")).chain_err(|| "parsing region failed")?;
-    let mandelbrot = Mandelbrot::new(region, width, height, max_iterations);
+    let global_arguments = arguments.subcommand().1.unwrap_or(&arguments);
+    let log_format = parsed_log_format(global_arguments.value_of("log-format").unwrap()).chain_err(|| "parsing log format failed")?;
+    let verbosity = global_arguments.occurrences_of("verbose") as i64 - global_arguments.occurrences_of("quiet") as i64;
+    logging::init(verbosity, log_format).chain_err(|| "initializing logging failed")?;
+
+    // Loaded after parsing the arguments, so --help works even with a broken config file
+    let config = Config::load().chain_err(|| "loading config failed")?;
+
+    match arguments.subcommand() {
+        ("bench", Some(bench_arguments)) => bench::run(bench_arguments, &config),
+        ("rerender", Some(rerender_arguments)) => rerender::run(rerender_arguments, &config),
+        ("tiles", Some(tiles_arguments)) => tiles::run(tiles_arguments, &config),
+        ("serve", Some(serve_arguments)) => serve::run(serve_arguments, &config),
+        ("cache", Some(cache_arguments)) => render_cache::run(cache_arguments),
+        ("animate", Some(animate_arguments)) => animate::run(animate_arguments, &config),
+        ("keyframes", Some(keyframes_arguments)) => keyframes::run(keyframes_arguments, &config),
+        ("expmap", Some(expmap_arguments)) => exponential_map::run(expmap_arguments, &config),
+        ("presets", Some(presets_arguments)) => config::run(presets_arguments, &config),
+        ("batch", Some(batch_arguments)) => batch::run(batch_arguments, &config),
+        ("explore", Some(explore_arguments)) => explore::run(explore_arguments, &config),
+        ("find-minibrot", Some(find_minibrot_arguments)) => find_minibrot::run(find_minibrot_arguments),
+        ("find-misiurewicz", Some(find_misiurewicz_arguments)) => find_misiurewicz::run(find_misiurewicz_arguments),
+        _ => run_render(&arguments, &config),
+    }
+}
 
 
-    let engine = parsed_engine(arguments.value_of("engine").unwrap(),
-                               arguments.is_present("parallel"),
-                               arguments.value_of("band-height").unwrap())
-        .chain_err(|| "parsing engine type failed")?;
+fn run_render(arguments: &ArgMatches, config: &Config) -> Result<()> {
+    let (width, height) = parsed_resolution(arguments.value_of("resolution").unwrap_or(config.resolution())).chain_err(|| "parsing resolution failed")?;
+    let max_iterations = match arguments.value_of("max-iterations") {
+        Some(max_iterations) => parsed_max_iterations(max_iterations).chain_err(|| "parsing max iterations failed")?,
+        None => config.max_iterations(),
+    };
+    let region = parsed_region(config, arguments.value_of("type"), arguments.value_of("center-and-radius")).chain_err(|| "parsing region failed")?;
+    let band_height = arguments.value_of("band-height").unwrap().parse::<u32>().chain_err(|| "invalid band size")?;
+
+    let parameters = RenderParameters {
+        region,
+        width,
+        height,
+        max_iterations,
+        engine_type: arguments.value_of("engine").unwrap_or(config.engine()).to_string(),
+        in_parallel: arguments.is_present("parallel"),
+        band_height,
+    };
 
     let output_filename = arguments.value_of("output-filename").unwrap();
+    let output_format = parsed_output_format(arguments.value_of("format"), output_filename).chain_err(|| "parsing output format failed")?;
+
+    // Text is rendered at the size of the terminal unless the resolution is given in characters
+    let parameters = match output_format.rows_per_character() {
+        Some(rows_per_character) => {
+            let grid = if arguments.occurrences_of("resolution") > 0 { CharacterGrid::new(width, height) } else { CharacterGrid::of_terminal() };
+
+            parameters.for_character_grid(&grid, rows_per_character)
+        },
+        None => parameters,
+    };
+    let data_output = match arguments.value_of("data-out") {
+        Some(data_filename) => Some(DataOutput::new(data_filename, arguments.value_of("data-values").unwrap()).chain_err(|| "parsing data output failed")?),
+        None => None,
+    };
+    let stats_filename = arguments.value_of("stats");
+
+    if arguments.is_present("stream") {
+        stream_mandelbrot_file(&parameters, output_filename, output_format, stats_filename)?;
+    } else {
+        let cache = if arguments.is_present("cache") { Some(RenderCache::open().chain_err(|| "opening render cache failed")?) } else { None };
+
+        create_mandelbrot_file(&parameters, output_filename, output_format, data_output.as_ref(), cache.as_ref(), stats_filename)?;
+    }
 
-    create_mandelbrot_file(&mandelbrot, &*engine, output_filename)?;
+    Ok(())
+}
+
+
+fn create_mandelbrot_file(parameters: &RenderParameters, output_filename: &str, output_format: OutputFormat, data_output: Option<&DataOutput>, cache: Option<&RenderCache>, stats_filename: Option<&str>) -> Result<()> {
+    let mandelbrot = parameters.mandelbrot();
+    let engine = parameters.engine().chain_err(|| "parsing engine type failed")?;
+
+    info!(target: "fractal::render",
+          event = "start",
+          engine = engine.name(),
+          parallel = engine.should_calculate_in_parallel(),
+          width = mandelbrot.width,
+          height = mandelbrot.height,
+          max_iterations = mandelbrot.max_iterations;
+          "render started");
+
+    let render_start = Instant::now();
+
+    let (pixels, mut stats) = match cache {
+        Some(cache) => cache.calculate(&mandelbrot, &*engine)?,
+        None => mandelbrot.calculate(&*engine),
+    };
+
+    let needs_smooth_values = output_format.needs_smooth_values() || data_output.is_some_and(|data_output| data_output.needs_smooth_values());
+
+    let smooth_values = if needs_smooth_values {
+        let timer = PhaseTimer::start("smooth");
+        let smooth_values = mandelbrot.calculate_smooth(&*engine);
+        stats.phases.push(timer.finish());
+
+        Some(smooth_values)
+    } else {
+        None
+    };
+
+    let timer = PhaseTimer::start("encode");
+    output_format::write_image(output_format,
+                               output_filename,
+                               mandelbrot.width,
+                               mandelbrot.height,
+                               &pixels,
+                               smooth_values.as_deref(),
+                               &parameters.to_text_chunks())?;
+    stats.phases.push(timer.finish());
+
+    if let Some(data_output) = data_output {
+        let timer = PhaseTimer::start("export");
+        data_export::write_data(data_output, &mandelbrot, &pixels, smooth_values.as_deref())?;
+        stats.phases.push(timer.finish());
+    }
+
+    for phase in &stats.phases {
+        log_phase_finished(phase);
+    }
+
+    info!(target: "fractal::render",
+          event = "finished",
+          output = output_filename,
+          elapsed_ms = elapsed_milliseconds(render_start);
+          "render finished");
+
+    if let Some(stats_filename) = stats_filename {
+        let stats_file = File::create(stats_filename)?;
+        serde_json::to_writer_pretty(stats_file, &stats)?;
+    }
 
     Ok(())
 }
 
 
-fn create_mandelbrot_file(mandelbrot: &Mandelbrot, engine: &MandelbrotEngine, output_filename: &str) -> Result<()> {
-    let pixels = mandelbrot.calculate(engine);
+fn stream_mandelbrot_file(parameters: &RenderParameters, output_filename: &str, output_format: OutputFormat, stats_filename: Option<&str>) -> Result<()> {
+    let mandelbrot = parameters.mandelbrot();
+    let engine = parameters.engine().chain_err(|| "parsing engine type failed")?;
+
+    info!(target: "fractal::render",
+          event = "start",
+          engine = engine.name(),
+          parallel = engine.should_calculate_in_parallel(),
+          width = mandelbrot.width,
+          height = mandelbrot.height,
+          max_iterations = mandelbrot.max_iterations,
+          band_height = parameters.band_height;
+          "streaming render started");
+
+    let render_start = Instant::now();
+
+    let mut image_stream = output_format::ImageStream::new(output_format,
+                                                           output_filename,
+                                                           mandelbrot.width,
+                                                           mandelbrot.height,
+                                                           &parameters.to_text_chunks())?;
+
+    let mut min_y = 0;
+    let stats = mandelbrot.calculate_bands(&*engine, parameters.band_height, |pixels| {
+        let rows = pixels.len() as u32 / mandelbrot.width;
+        let smooth_values = if output_format.needs_smooth_values() {
+            Some(mandelbrot.calculate_smooth_window(&*engine, &Window::new(0, min_y, mandelbrot.width, rows)))
+        } else {
+            None
+        };
+        min_y += rows;
+
+        image_stream.write_rows(pixels, smooth_values.as_deref())
+    })?;
+    image_stream.finish()?;
+
+    for phase in &stats.phases {
+        log_phase_finished(phase);
+    }
+
+    info!(target: "fractal::render",
+          event = "finished",
+          output = output_filename,
+          elapsed_ms = elapsed_milliseconds(render_start);
+          "render finished");
 
-    let output = File::create(output_filename)?;
-    let png_encoder = PNGEncoder::new(output);
-    png_encoder.encode(pixels.as_slice(), mandelbrot.width, mandelbrot.height, ColorType::Gray(8))?;
+    if let Some(stats_filename) = stats_filename {
+        let stats_file = File::create(stats_filename)?;
+        serde_json::to_writer_pretty(stats_file, &stats)?;
+    }
 
     Ok(())
 }
 
 
+fn log_phase_finished(phase: &PhaseTiming) {
+    // The CPU time is unknown on platforms without a process clock
+    match phase.cpu_ms {
+        Some(cpu_ms) => info!(target: "fractal::render",
+                              event = "phase",
+                              phase = phase.name.as_str(),
+                              elapsed_ms = phase.wall_ms,
+                              cpu_ms = cpu_ms;
+                              "phase {} finished", phase.name),
+        None => info!(target: "fractal::render",
+                      event = "phase",
+                      phase = phase.name.as_str(),
+                      elapsed_ms = phase.wall_ms;
+                      "phase {} finished", phase.name),
+    }
+}
+
+
+fn elapsed_milliseconds(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+
 fn parsed_resolution(resolution: &str) -> Result<(u32, u32)> {
     let tokens: Vec<&str> = resolution
         .split('x')
@@ -154,6 +455,10 @@ fn parsed_resolution(resolution: &str) -> Result<(u32, u32)> {
     let width = tokens[0].parse::<u32>().chain_err(|| "invalid width")?;
     let height = tokens[1].parse::<u32>().chain_err(|| "invalid height")?;
 
+    if width == 0 || height == 0 {
+        bail!("width and height have to be positive");
+    }
+
     Ok((width, height))
 }
 
@@ -165,44 +470,61 @@ fn parsed_max_iterations(max_iterations: &str) -> Result<u8> {
 }
 
 
-fn parsed_region(region_type: Option<&str>, center_and_radius: Option<&str>) -> Result<Region> {
+fn parsed_region(config: &Config, region_type: Option<&str>, center_and_radius: Option<&str>) -> Result<Region> {
+    // The region type has a default value, so an explicit center and radius takes precedence
+    if let Some(center_and_radius) = center_and_radius {
+        return parsed_center_and_radius(center_and_radius);
+    }
+
     if let Some(region_type) = region_type {
-        let region_type = match region_type {
-            "SeaHorseValley" => RegionType::SeaHorseValley,
-            "Default" => RegionType::Default,
-            _ => bail!("unsupported region type")
-        };
+        return config.region(region_type).ok_or_else(|| "unsupported region type or preset".into());
+    }
+
+    bail!("either region or center/radius have to be specified");
+}
+
+
+fn parsed_center_and_radius(center_and_radius: &str) -> Result<Region> {
+    let separators: &[char] = &['/', '@'];
+    let tokens: Vec<&str> = center_and_radius.split(separators).collect();
 
-        return Ok(Region::new_for_type(region_type));
+    if tokens.len() != 3 {
+        bail!("format for center and radius should be: <real>/<imag>@radius" );
     }
 
-    if let Some(center_and_radius) = center_and_radius {
-        let separators: &[char] = &['/', '@'];
-        let tokens: Vec<&str> = center_and_radius.split(separators).collect();
+    let center_re = tokens[0].parse::<f64>().chain_err(|| "invalid center_re")?;
+    let center_im = tokens[1].parse::<f64>().chain_err(|| "invalid center_im")?;
+    let radius = tokens[2].parse::<f64>().chain_err(|| "invalid radius")?;
 
-        if tokens.len() != 3 {
-            bail!("format for center and radius should be: <real>/<imag>@radius" );
-        }
+    Ok(Region::new_for_center(Complex64::new(center_re, center_im), radius))
+}
 
-        let center_re = tokens[0].parse::<f64>().chain_err(|| "invalid center_re")?;
-        let center_im = tokens[1].parse::<f64>().chain_err(|| "invalid center_im")?;
-        let radius = tokens[2].parse::<f64>().chain_err(|| "invalid radius")?;
 
-        return Ok(Region::new_for_center(Complex64::new(center_re, center_im), radius));
+fn parsed_output_format(format: Option<&str>, output_filename: &str) -> Result<OutputFormat> {
+    match format {
+        Some(format) => OutputFormat::for_name(format),
+        None => OutputFormat::for_filename(output_filename),
     }
+}
 
-    bail!("either region or center/radius have to be specified");
+
+fn parsed_log_format(log_format: &str) -> Result<LogFormat> {
+    match log_format {
+        "text" => Ok(LogFormat::Text),
+        "json" => Ok(LogFormat::Json),
+        _ => bail!("unsupported log format")
+    }
 }
 
 
-fn parsed_engine(engine_type: &str, in_parallel: bool, band_height: &str) -> Result<Box<MandelbrotEngine>> {
-    match engine_type {
-        "Default" => Ok(Box::new(SimpleMandelbrotEngine::new(in_parallel))),
-        "Recursive" => {
+fn parsed_engine(engine_type: &str, in_parallel: bool, band_height: &str) -> Result<Box<dyn MandelbrotEngine>> {
+    match EngineType::for_name(engine_type) {
+        Some(EngineType::Simple) => Ok(EngineType::Simple.engine(in_parallel, 0)),
+        Some(EngineType::Recursive) => {
             let band_height = band_height.parse::<u32>().chain_err(|| "invalid band size")?;
 
-            Ok(Box::new(RecursiveMandelbrotEngine::new(in_parallel, band_height)))
+            Ok(EngineType::Recursive.engine(in_parallel, band_height))
         },
-        _ => bail!("unsupported engine type")
+        None => bail!("unsupported engine type")
     }
 }
@@ -0,0 +1,300 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use exr::prelude::{Image, SpecificChannels, WritableImage};
+use png;
+use tiff::encoder::{TiffEncoder, colortype};
+
+use errors::*;
+use fractal::coloring;
+use png_file;
+use text_art::{self, AnsiColors};
+
+
+// Output file name which makes the image being written to stdout
+pub const STDOUT_FILENAME: &str = "-";
+const SIXTEEN_BIT_ERROR: &str = "smooth iteration values are required for 16 bit output";
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum OutputFormat {
+    Png,
+    Png16,
+    Png16Rgb,
+    Tiff,
+    Pgm,
+    Ppm,
+    Exr,
+    Ascii,
+    Ansi,
+}
+
+
+impl OutputFormat {
+    pub fn for_name(name: &str) -> Result<OutputFormat> {
+        match name {
+            "png" => Ok(OutputFormat::Png),
+            "png16" => Ok(OutputFormat::Png16),
+            "png16-rgb" => Ok(OutputFormat::Png16Rgb),
+            "tiff" => Ok(OutputFormat::Tiff),
+            "pgm" => Ok(OutputFormat::Pgm),
+            "ppm" => Ok(OutputFormat::Ppm),
+            "exr" => Ok(OutputFormat::Exr),
+            "ascii" => Ok(OutputFormat::Ascii),
+            "ansi" => Ok(OutputFormat::Ansi),
+            _ => bail!("unsupported output format")
+        }
+    }
+
+
+    pub fn for_filename(filename: &str) -> Result<OutputFormat> {
+        if filename == STDOUT_FILENAME {
+            bail!("output format has to be specified when writing to stdout");
+        }
+
+        let extension = Path::new(filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+
+        match extension.as_deref() {
+            Some("png") => Ok(OutputFormat::Png),
+            Some("tif") | Some("tiff") => Ok(OutputFormat::Tiff),
+            Some("pgm") => Ok(OutputFormat::Pgm),
+            Some("ppm") => Ok(OutputFormat::Ppm),
+            Some("exr") => Ok(OutputFormat::Exr),
+            Some("txt") => Ok(OutputFormat::Ascii),
+            Some("ans") => Ok(OutputFormat::Ansi),
+            _ => bail!("output format cannot be derived from the file name extension")
+        }
+    }
+
+
+    pub fn needs_smooth_values(&self) -> bool {
+        matches!(*self, OutputFormat::Png16 | OutputFormat::Png16Rgb | OutputFormat::Exr)
+    }
+
+
+    // Rows of pixels per line of text (two for half blocks), None for image formats
+    pub fn rows_per_character(&self) -> Option<u32> {
+        match *self {
+            OutputFormat::Ascii => Some(1),
+            OutputFormat::Ansi => Some(2),
+            _ => None,
+        }
+    }
+
+
+    fn supports_stdout(&self) -> bool {
+        !matches!(*self, OutputFormat::Tiff | OutputFormat::Exr)
+    }
+}
+
+
+pub fn write_image(format: OutputFormat, output_filename: &str, width: u32, height: u32, pixels: &[u8], smooth_values: Option<&[f32]>, text_chunks: &[(String, String)]) -> Result<()> {
+    if output_filename == STDOUT_FILENAME {
+        if !format.supports_stdout() {
+            bail!("output format cannot be written to stdout");
+        }
+
+        let stdout = io::stdout();
+        return write_streamable_image(format, stdout.lock(), width, height, pixels, smooth_values, text_chunks);
+    }
+
+    match format {
+        OutputFormat::Tiff => {
+            let mut output = BufWriter::new(File::create(output_filename)?);
+            TiffEncoder::new(&mut output)?.write_image::<colortype::Gray8>(width, height, pixels)?;
+            output.flush()?;
+
+            Ok(())
+        },
+        OutputFormat::Exr => {
+            let smooth_values = smooth_values.ok_or("smooth iteration values are required for OpenEXR output")?;
+            let channels = SpecificChannels::build()
+                .with_channel("Y")
+                .with_pixel_fn(|position| (smooth_values[position.y() * width as usize + position.x()],));
+
+            Image::from_channels((width as usize, height as usize), channels)
+                .write()
+                .to_file(output_filename)?;
+
+            Ok(())
+        },
+        _ => write_streamable_image(format, BufWriter::new(File::create(output_filename)?), width, height, pixels, smooth_values, text_chunks)
+    }
+}
+
+
+fn write_streamable_image<W: Write>(format: OutputFormat, mut output: W, width: u32, height: u32, pixels: &[u8], smooth_values: Option<&[f32]>, text_chunks: &[(String, String)]) -> Result<()> {
+    match format {
+        OutputFormat::Png => {
+            png_file::write(output, width, height, png::ColorType::Grayscale, png::BitDepth::Eight, pixels, text_chunks)
+        },
+        OutputFormat::Png16 => {
+            let data = coloring::sixteen_bit_samples(smooth_values.ok_or(SIXTEEN_BIT_ERROR)?, 1);
+            png_file::write(output, width, height, png::ColorType::Grayscale, png::BitDepth::Sixteen, &data, text_chunks)
+        },
+        OutputFormat::Png16Rgb => {
+            let data = coloring::sixteen_bit_samples(smooth_values.ok_or(SIXTEEN_BIT_ERROR)?, 3);
+            png_file::write(output, width, height, png::ColorType::Rgb, png::BitDepth::Sixteen, &data, text_chunks)
+        },
+        OutputFormat::Pgm => {
+            write!(output, "P5\n{} {}\n255\n", width, height)?;
+            output.write_all(pixels)?;
+            output.flush()?;
+
+            Ok(())
+        },
+        OutputFormat::Ppm => {
+            write!(output, "P6\n{} {}\n255\n", width, height)?;
+            for &pixel in pixels {
+                output.write_all(&[pixel, pixel, pixel])?;
+            }
+            output.flush()?;
+
+            Ok(())
+        },
+        OutputFormat::Ascii | OutputFormat::Ansi => {
+            let lines = match format {
+                OutputFormat::Ascii => text_art::ascii_lines(pixels, width as usize),
+                _ => text_art::half_block_lines(pixels, width as usize, height as usize, AnsiColors::detected()),
+            };
+
+            for line in lines {
+                writeln!(output, "{}", line)?;
+            }
+            output.flush()?;
+
+            Ok(())
+        },
+        OutputFormat::Tiff | OutputFormat::Exr => bail!("output format cannot be streamed")
+    }
+}
+
+
+// Writes an image band by band, so the whole image never has to be held in memory
+pub struct ImageStream {
+    format: OutputFormat,
+    encoder: StreamEncoder,
+}
+
+
+enum StreamEncoder {
+    Png(Box<png::StreamWriter<'static, Box<dyn Write>>>),
+    Pnm(Box<dyn Write>),
+}
+
+
+impl ImageStream {
+    pub fn new(format: OutputFormat, output_filename: &str, width: u32, height: u32, text_chunks: &[(String, String)]) -> Result<ImageStream> {
+        let output: Box<dyn Write> = if output_filename == STDOUT_FILENAME {
+            Box::new(BufWriter::new(io::stdout()))
+        } else {
+            Box::new(BufWriter::new(File::create(output_filename)?))
+        };
+
+        let encoder = match format {
+            OutputFormat::Png => {
+                StreamEncoder::Png(Box::new(png_file::stream_writer(output, width, height, png::ColorType::Grayscale, png::BitDepth::Eight, text_chunks)?))
+            },
+            OutputFormat::Png16 => {
+                StreamEncoder::Png(Box::new(png_file::stream_writer(output, width, height, png::ColorType::Grayscale, png::BitDepth::Sixteen, text_chunks)?))
+            },
+            OutputFormat::Png16Rgb => {
+                StreamEncoder::Png(Box::new(png_file::stream_writer(output, width, height, png::ColorType::Rgb, png::BitDepth::Sixteen, text_chunks)?))
+            },
+            OutputFormat::Pgm => {
+                let mut output = output;
+                write!(output, "P5\n{} {}\n255\n", width, height)?;
+                StreamEncoder::Pnm(output)
+            },
+            OutputFormat::Ppm => {
+                let mut output = output;
+                write!(output, "P6\n{} {}\n255\n", width, height)?;
+                StreamEncoder::Pnm(output)
+            },
+            _ => bail!("output format cannot be streamed (values: png, png16, png16-rgb, pgm, ppm)")
+        };
+
+        Ok(ImageStream { format, encoder })
+    }
+
+
+    // Smooth escape times of the rows are only required for formats which need them
+    pub fn write_rows(&mut self, pixels: &[u8], smooth_values: Option<&[f32]>) -> Result<()> {
+        let data = match self.format {
+            OutputFormat::Png16 => coloring::sixteen_bit_samples(smooth_values.ok_or(SIXTEEN_BIT_ERROR)?, 1),
+            OutputFormat::Png16Rgb => coloring::sixteen_bit_samples(smooth_values.ok_or(SIXTEEN_BIT_ERROR)?, 3),
+            OutputFormat::Ppm => {
+                let mut data: Vec<u8> = Vec::with_capacity(pixels.len() * 3);
+                for &pixel in pixels {
+                    data.extend_from_slice(&[pixel, pixel, pixel]);
+                }
+                data
+            },
+            _ => pixels.to_vec(),
+        };
+
+        match self.encoder {
+            StreamEncoder::Png(ref mut writer) => writer.write_all(&data)?,
+            StreamEncoder::Pnm(ref mut output) => output.write_all(&data)?,
+        }
+
+        Ok(())
+    }
+
+
+    pub fn finish(self) -> Result<()> {
+        match self.encoder {
+            StreamEncoder::Png(writer) => writer.finish()?,
+            StreamEncoder::Pnm(mut output) => output.flush()?,
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn for_filename_derives_format_from_extension() {
+        // act & assert
+        assert_eq!(OutputFormat::for_filename("image.png").unwrap(), OutputFormat::Png);
+        assert_eq!(OutputFormat::for_filename("image.TIF").unwrap(), OutputFormat::Tiff);
+        assert_eq!(OutputFormat::for_filename("image.pgm").unwrap(), OutputFormat::Pgm);
+        assert_eq!(OutputFormat::for_filename("image.exr").unwrap(), OutputFormat::Exr);
+    }
+
+    #[test]
+    fn for_filename_for_stdout_fails() {
+        // act
+        let result = OutputFormat::for_filename(STDOUT_FILENAME);
+
+        // assert
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn image_stream_writes_same_pgm_as_write_image() {
+        // arrange
+        let directory = ::std::env::temp_dir();
+        let streamed_filename = directory.join("fractal_stream_test_streamed.pgm");
+        let written_filename = directory.join("fractal_stream_test_written.pgm");
+        let pixels: Vec<u8> = (0..12).collect();
+
+        // act
+        let mut stream = ImageStream::new(OutputFormat::Pgm, streamed_filename.to_str().unwrap(), 4, 3, &[]).unwrap();
+        stream.write_rows(&pixels[..8], None).unwrap();
+        stream.write_rows(&pixels[8..], None).unwrap();
+        stream.finish().unwrap();
+        write_image(OutputFormat::Pgm, written_filename.to_str().unwrap(), 4, 3, &pixels, None, &[]).unwrap();
+
+        // assert
+        assert_eq!(::std::fs::read(&streamed_filename).unwrap(), ::std::fs::read(&written_filename).unwrap());
+    }
+}
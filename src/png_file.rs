@@ -0,0 +1,77 @@
+use std::fs::File;
+use std::io::{BufReader, Write};
+
+use png;
+
+use errors::*;
+
+
+pub fn write<W: Write>(output: W, width: u32, height: u32, color_type: png::ColorType, bit_depth: png::BitDepth, data: &[u8], text_chunks: &[(String, String)]) -> Result<()> {
+    let mut writer = encoder(output, width, height, color_type, bit_depth, text_chunks)?.write_header()?;
+    writer.write_image_data(data)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+
+// Returns a writer which accepts the image data row by row, it has to be finished after the last row
+pub fn stream_writer<W: Write>(output: W, width: u32, height: u32, color_type: png::ColorType, bit_depth: png::BitDepth, text_chunks: &[(String, String)]) -> Result<png::StreamWriter<'static, W>> {
+    let writer = encoder(output, width, height, color_type, bit_depth, text_chunks)?.write_header()?;
+
+    Ok(writer.into_stream_writer()?)
+}
+
+
+fn encoder<W: Write>(output: W, width: u32, height: u32, color_type: png::ColorType, bit_depth: png::BitDepth, text_chunks: &[(String, String)]) -> Result<png::Encoder<'static, W>> {
+    let mut encoder = png::Encoder::new(output, width, height);
+    encoder.set_color(color_type);
+    encoder.set_depth(bit_depth);
+
+    for (keyword, text) in text_chunks {
+        encoder.add_text_chunk(keyword.clone(), text.clone())?;
+    }
+
+    Ok(encoder)
+}
+
+
+pub fn read_text_chunks(input_filename: &str) -> Result<Vec<(String, String)>> {
+    let input = BufReader::new(File::open(input_filename)?);
+    let reader = png::Decoder::new(input).read_info()?;
+    let info = reader.info();
+
+    let latin1_chunks = info.uncompressed_latin1_text.iter()
+        .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()));
+    let utf8_chunks = info.utf8_text.iter()
+        .filter_map(|chunk| chunk.get_text().ok().map(|text| (chunk.keyword.clone(), text)));
+
+    Ok(latin1_chunks.chain(utf8_chunks).collect())
+}
+
+
+// Returns width, height and pixels of an 8 bit grayscale image
+pub fn read_grayscale(input_filename: &str) -> Result<(u32, u32, Vec<u8>)> {
+    let input = BufReader::new(File::open(input_filename)?);
+    let mut reader = png::Decoder::new(input).read_info()?;
+
+    if reader.output_color_type() != (png::ColorType::Grayscale, png::BitDepth::Eight) {
+        bail!("only 8 bit grayscale images are supported");
+    }
+
+    let mut pixels = vec![0; reader.output_buffer_size().ok_or("image too large")?];
+    let info = reader.next_frame(&mut pixels)?;
+    pixels.truncate(info.buffer_size());
+
+    Ok((info.width, info.height, pixels))
+}
+
+
+// Returns a writer for an APNG of 8 bit grayscale frames, each frame is written by one call of write_image_data
+pub fn animated_writer<W: Write>(output: W, width: u32, height: u32, frame_count: u32, loops: u32, fps: u16, text_chunks: &[(String, String)]) -> Result<png::Writer<W>> {
+    let mut encoder = encoder(output, width, height, png::ColorType::Grayscale, png::BitDepth::Eight, text_chunks)?;
+    encoder.set_animated(frame_count, loops)?;
+    encoder.set_frame_delay(1, fps)?;
+
+    Ok(encoder.write_header()?)
+}
@@ -0,0 +1,176 @@
+// Python module of the renderer, built with the python feature, i.e. by maturin (see pyproject.toml)
+use num::complex::Complex64;
+use numpy::ndarray::Array2;
+use numpy::{IntoPyArray, PyArray2};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use fractal::prelude::*;
+
+
+#[pyclass(name = "RegionType", eq, eq_int, frozen)]
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum PyRegionType {
+    Default,
+    SeaHorseValley,
+    ElephantValley,
+    TripleSpiralValley,
+    ScepterValley,
+    MiniMandelbrot,
+    SeaHorseSpiral,
+}
+
+
+#[pyclass(name = "Region", frozen)]
+#[derive(Debug, Copy, Clone)]
+struct PyRegion {
+    region: Region,
+}
+
+
+impl PyRegionType {
+    fn region_type(self) -> RegionType {
+        match self {
+            PyRegionType::Default => RegionType::Default,
+            PyRegionType::SeaHorseValley => RegionType::SeaHorseValley,
+            PyRegionType::ElephantValley => RegionType::ElephantValley,
+            PyRegionType::TripleSpiralValley => RegionType::TripleSpiralValley,
+            PyRegionType::ScepterValley => RegionType::ScepterValley,
+            PyRegionType::MiniMandelbrot => RegionType::MiniMandelbrot,
+            PyRegionType::SeaHorseSpiral => RegionType::SeaHorseSpiral,
+        }
+    }
+}
+
+
+#[pymethods]
+impl PyRegionType {
+    #[getter]
+    fn description(&self) -> &'static str {
+        self.region_type().description()
+    }
+}
+
+
+#[pymethods]
+impl PyRegion {
+    #[new]
+    fn new(min_re: f64, max_re: f64, min_im: f64, max_im: f64) -> PyRegion {
+        PyRegion { region: Region::new(min_re, max_re, min_im, max_im) }
+    }
+
+
+    #[staticmethod]
+    fn for_center(center: Complex64, radius: f64) -> PyResult<PyRegion> {
+        Ok(PyRegion { region: Region::new_for_center(center, positive_radius(radius)?) })
+    }
+
+
+    #[staticmethod]
+    fn for_type(region_type: PyRegionType) -> PyRegion {
+        PyRegion { region: Region::new_for_type(region_type.region_type()) }
+    }
+
+
+    // The region type names of the command line, e.g. "SeaHorseValley"
+    #[staticmethod]
+    fn for_name(name: &str) -> PyResult<PyRegion> {
+        match RegionType::for_name(name) {
+            Some(region_type) => Ok(PyRegion { region: Region::new_for_type(region_type) }),
+            None => Err(PyValueError::new_err(format!("unsupported region type {}", name))),
+        }
+    }
+
+
+    #[getter]
+    fn min_re(&self) -> f64 {
+        self.region.min_re()
+    }
+
+
+    #[getter]
+    fn max_re(&self) -> f64 {
+        self.region.min_re() + self.region.width()
+    }
+
+
+    #[getter]
+    fn min_im(&self) -> f64 {
+        self.region.min_im()
+    }
+
+
+    #[getter]
+    fn max_im(&self) -> f64 {
+        self.region.min_im() + self.region.height()
+    }
+
+
+    fn __repr__(&self) -> String {
+        format!("Region({}, {}, {}, {})", self.min_re(), self.max_re(), self.min_im(), self.max_im())
+    }
+}
+
+
+// Escape times of the pixels as an array of height rows of width pixels, max_iterations for points inside the set
+#[pyfunction]
+#[pyo3(signature = (center, radius, width, height, engine = "default", parallel = false, max_iterations = 255, band_height = 64))]
+#[allow(clippy::too_many_arguments)]
+fn render<'py>(py: Python<'py>, center: Complex64, radius: f64, width: u32, height: u32, engine: &str, parallel: bool, max_iterations: u8,
+               band_height: u32) -> PyResult<Bound<'py, PyArray2<u8>>> {
+    let region = Region::new_for_center(center, positive_radius(radius)?);
+
+    render_region(py, PyRegion { region }, width, height, engine, parallel, max_iterations, band_height)
+}
+
+
+#[pyfunction]
+#[pyo3(signature = (region, width, height, engine = "default", parallel = false, max_iterations = 255, band_height = 64))]
+#[allow(clippy::too_many_arguments)]
+fn render_region<'py>(py: Python<'py>, region: PyRegion, width: u32, height: u32, engine: &str, parallel: bool, max_iterations: u8,
+                      band_height: u32) -> PyResult<Bound<'py, PyArray2<u8>>> {
+    let engine_type = EngineType::all().iter().cloned()
+        .find(|engine_type| engine_type.name().eq_ignore_ascii_case(engine))
+        .ok_or_else(|| PyValueError::new_err(format!("unsupported engine type {}", engine)))?;
+
+    if width == 0 || height == 0 || max_iterations == 0 || band_height == 0 {
+        return Err(PyValueError::new_err("width, height, max_iterations and band_height have to be positive"));
+    }
+
+    let renderer = Renderer::new()
+        .region(region.region)
+        .size(width, height)
+        .max_iterations(max_iterations)
+        .engine_type(engine_type)
+        .parallel(parallel)
+        .band_height(band_height);
+
+    // Other Python threads keep running while rendering
+    let image = py.detach(move || renderer.render());
+
+    let pixels = Array2::from_shape_vec((height as usize, width as usize), image.pixels)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+    Ok(pixels.into_pyarray(py))
+}
+
+
+fn positive_radius(radius: f64) -> PyResult<f64> {
+    if radius > 0.0 {
+        Ok(radius)
+    } else {
+        Err(PyValueError::new_err("radius has to be positive"))
+    }
+}
+
+
+#[pymodule]
+fn fractal(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyRegionType>()?;
+    module.add_class::<PyRegion>()?;
+    module.add_function(wrap_pyfunction!(python::render, module)?)?;
+    module.add_function(wrap_pyfunction!(python::render_region, module)?)?;
+    module.add("ENGINES", EngineType::all().iter().map(|engine_type| engine_type.name().to_lowercase()).collect::<Vec<String>>())?;
+
+    Ok(())
+}
@@ -0,0 +1,507 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+use clap::{App, AppSettings, ArgMatches, SubCommand};
+use serde_json;
+
+use errors::*;
+use fractal::prelude::*;
+use render_parameters::FORMULA;
+
+
+const CACHE_DIRECTORY_VARIABLE: &str = "FRACTAL_CACHE_DIR";
+const CACHE_LIMIT_VARIABLE: &str = "FRACTAL_CACHE_LIMIT_MB";
+const DEFAULT_CACHE_LIMIT_MB: u64 = 1024;
+const ENTRY_MAGIC: &[u8] = b"FRACCACH";
+const ENTRY_EXTENSION: &str = "cache";
+const COUNTERS_FILENAME: &str = "counters.json";
+
+
+// Renders in parallel (i.e. the jobs of a batch) share the cache: files are written under unique temporary names and
+// renamed into place, entries evicted by another render count as missing, and counter updates are serialized. Counter
+// updates of concurrent processes can still get lost, they are statistics only.
+static TEMPORARY_FILES: AtomicUsize = AtomicUsize::new(0);
+static COUNTERS_LOCK: Mutex<()> = Mutex::new(());
+
+
+// Iteration buffers are stored on disk under a hash of everything that determines their content
+pub struct RenderCache {
+    pub directory: PathBuf,
+    pub limit_bytes: u64,
+}
+
+
+// Everything the iteration buffer depends on; the recursive engine fills uniform areas, which can differ from the
+// escape times of the simple engine at the edges, and depends on the band height
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct CacheKey {
+    formula: String,
+    engine: String,
+    band_height: Option<u32>,
+    min_re: f64,
+    min_im: f64,
+    region_width: f64,
+    region_height: f64,
+    width: u32,
+    height: u32,
+    max_iterations: u8,
+    rotation: f64,
+    julia_constant: Option<(f64, f64)>,
+    exponential_map_center: Option<(f64, f64)>,
+    precision: String,
+    samples: String,
+}
+
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheCounters {
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+
+struct CacheEntry {
+    path: PathBuf,
+    size: u64,
+    last_used: SystemTime,
+}
+
+
+impl CacheKey {
+    pub fn new(mandelbrot: &Mandelbrot, engine: &dyn MandelbrotEngine) -> CacheKey {
+        CacheKey {
+            formula: FORMULA.to_string(),
+            engine: engine.name().to_string(),
+            band_height: engine.band_height(),
+            min_re: mandelbrot.region.min_re(),
+            min_im: mandelbrot.region.min_im(),
+            region_width: mandelbrot.region.width(),
+            region_height: mandelbrot.region.height(),
+            width: mandelbrot.width,
+            height: mandelbrot.height,
+            max_iterations: mandelbrot.max_iterations,
+            rotation: mandelbrot.rotation,
+            julia_constant: mandelbrot.julia_constant.map(|constant| (constant.re, constant.im)),
+            exponential_map_center: mandelbrot.exponential_map_center.map(|center| (center.re, center.im)),
+            precision: "f64".to_string(),
+            samples: "1 per pixel".to_string(),
+        }
+    }
+
+
+    pub fn hash(&self) -> String {
+        let description = serde_json::to_vec(self).expect("cache key should be serializable");
+
+        format!("{:016x}", fnv1a_hash(&description))
+    }
+}
+
+
+impl RenderCache {
+    // Uses $FRACTAL_CACHE_DIR, $XDG_CACHE_HOME/fractal or ~/.cache/fractal and a limit of $FRACTAL_CACHE_LIMIT_MB
+    pub fn open() -> Result<RenderCache> {
+        let directory = match (env::var_os(CACHE_DIRECTORY_VARIABLE), env::var_os("XDG_CACHE_HOME"), env::var_os("HOME")) {
+            (Some(directory), _, _) => PathBuf::from(directory),
+            (None, Some(cache_home), _) => PathBuf::from(cache_home).join("fractal"),
+            (None, None, Some(home)) => PathBuf::from(home).join(".cache").join("fractal"),
+            (None, None, None) => bail!("cache directory cannot be determined, set {}", CACHE_DIRECTORY_VARIABLE),
+        };
+
+        let limit_mb = match env::var(CACHE_LIMIT_VARIABLE) {
+            Ok(limit_mb) => limit_mb.parse::<u64>().chain_err(|| format!("invalid {}", CACHE_LIMIT_VARIABLE))?,
+            Err(_) => DEFAULT_CACHE_LIMIT_MB,
+        };
+
+        Ok(RenderCache { directory, limit_bytes: limit_mb * 1024 * 1024 })
+    }
+
+
+    // Returns the cached iteration buffer or calculates and stores it
+    pub fn calculate(&self, mandelbrot: &Mandelbrot, engine: &dyn MandelbrotEngine) -> Result<(Vec<u8>, RenderStats)> {
+        let key = CacheKey::new(mandelbrot, engine);
+        let path = self.entry_path(&key);
+
+        if let Some(pixels) = self.load(&path, &key)? {
+            info!(event = "cache_hit", key = key.hash().as_str(); "using cached iterations {}", key.hash());
+            self.count(|counters| counters.hits += 1)?;
+
+            let timer = PhaseTimer::start("cache");
+            let mut stats = RenderStats::new();
+            stats.record_pixels(&pixels, mandelbrot.max_iterations);
+            stats.phases.push(timer.finish());
+
+            return Ok((pixels, stats));
+        }
+
+        self.count(|counters| counters.misses += 1)?;
+
+        let (pixels, stats) = mandelbrot.calculate(engine);
+        self.store(&path, &key, &pixels)?;
+
+        Ok((pixels, stats))
+    }
+
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.directory.join(format!("{}.{}", key.hash(), ENTRY_EXTENSION))
+    }
+
+
+    fn load(&self, path: &Path, key: &CacheKey) -> Result<Option<Vec<u8>>> {
+        let (stored_key, pixels) = match read_entry(path) {
+            Ok(entry) => entry,
+            Err(Error(ErrorKind::Io(ref e), _)) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        // A hash collision must not deliver the wrong image
+        if stored_key != *key {
+            return Ok(None);
+        }
+
+        ignoring_not_found(File::options().write(true).open(path).and_then(|file| file.set_modified(SystemTime::now())))?;
+
+        Ok(Some(pixels))
+    }
+
+
+    fn store(&self, path: &Path, key: &CacheKey, pixels: &[u8]) -> Result<()> {
+        if pixels.len() as u64 > self.limit_bytes {
+            warn!("iterations of {}x{} pixels exceed the cache limit and are not cached", key.width, key.height);
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.directory)?;
+
+        let json_key = serde_json::to_vec(key)?;
+        let temporary_path = temporary_path(path);
+        {
+            let mut output = BufWriter::new(File::create(&temporary_path)?);
+            output.write_all(ENTRY_MAGIC)?;
+            output.write_all(&(json_key.len() as u32).to_le_bytes())?;
+            output.write_all(&json_key)?;
+            output.write_all(pixels)?;
+            output.flush()?;
+        }
+        fs::rename(&temporary_path, path)?;
+
+        self.evict()
+    }
+
+
+    // Removes the least recently used entries until the cache fits into its limit
+    fn evict(&self) -> Result<()> {
+        let mut entries = self.entries()?;
+        let mut total_size: u64 = entries.iter().map(|entry| entry.size).sum();
+        let mut evictions = 0;
+
+        entries.sort_by_key(|entry| entry.last_used);
+
+        for entry in entries {
+            if total_size <= self.limit_bytes {
+                break;
+            }
+
+            // Another render may have evicted it already
+            if ignoring_not_found(fs::remove_file(&entry.path))?.is_some() {
+                evictions += 1;
+            }
+            total_size -= entry.size;
+        }
+
+        if evictions > 0 {
+            debug!(event = "cache_evicted", entries = evictions; "evicted {} cache entries", evictions);
+            self.count(|counters| counters.evictions += evictions)?;
+        }
+
+        Ok(())
+    }
+
+
+    fn entries(&self) -> Result<Vec<CacheEntry>> {
+        if !self.directory.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries: Vec<CacheEntry> = Vec::new();
+
+        for dir_entry in fs::read_dir(&self.directory)? {
+            let path = dir_entry?.path();
+
+            if path.extension().and_then(|extension| extension.to_str()) != Some(ENTRY_EXTENSION) {
+                continue;
+            }
+
+            if let Some(metadata) = ignoring_not_found(fs::metadata(&path))? {
+                entries.push(CacheEntry { path, size: metadata.len(), last_used: metadata.modified()? });
+            }
+        }
+
+        Ok(entries)
+    }
+
+
+    fn counters(&self) -> CacheCounters {
+        File::open(self.directory.join(COUNTERS_FILENAME))
+            .ok()
+            .and_then(|file| serde_json::from_reader(file).ok())
+            .unwrap_or_default()
+    }
+
+
+    fn count<F: FnOnce(&mut CacheCounters)>(&self, update: F) -> Result<()> {
+        let _lock = COUNTERS_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let mut counters = self.counters();
+        update(&mut counters);
+
+        fs::create_dir_all(&self.directory)?;
+
+        let path = self.directory.join(COUNTERS_FILENAME);
+        let temporary_path = temporary_path(&path);
+        serde_json::to_writer(File::create(&temporary_path)?, &counters)?;
+        fs::rename(&temporary_path, &path)?;
+
+        Ok(())
+    }
+}
+
+
+// Unique per process and write, so concurrent writes of the same file never share a temporary file
+fn temporary_path(path: &Path) -> PathBuf {
+    path.with_extension(format!("{}-{}.partial", process::id(), TEMPORARY_FILES.fetch_add(1, Ordering::Relaxed)))
+}
+
+
+fn ignoring_not_found<T>(result: io::Result<T>) -> io::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+
+fn read_entry(path: &Path) -> Result<(CacheKey, Vec<u8>)> {
+    let mut input = BufReader::new(File::open(path)?);
+
+    let mut magic = [0u8; 8];
+    input.read_exact(&mut magic)?;
+
+    if magic != ENTRY_MAGIC {
+        bail!("{} is not a cache entry", path.display());
+    }
+
+    let mut json_length = [0u8; 4];
+    input.read_exact(&mut json_length)?;
+
+    let mut json_key = vec![0u8; u32::from_le_bytes(json_length) as usize];
+    input.read_exact(&mut json_key)?;
+
+    let mut pixels: Vec<u8> = Vec::new();
+    input.read_to_end(&mut pixels)?;
+
+    Ok((serde_json::from_slice(&json_key)?, pixels))
+}
+
+
+// FNV-1a is stable across Rust versions and platforms, unlike the standard library's hasher
+fn fnv1a_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("cache")
+        .about("Manages the on-disk cache of iteration buffers (directory: $FRACTAL_CACHE_DIR, limit: $FRACTAL_CACHE_LIMIT_MB)")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("ls")
+            .about("Lists the cached iteration buffers, least recently used first"))
+        .subcommand(SubCommand::with_name("clear")
+            .about("Removes all cached iteration buffers"))
+        .subcommand(SubCommand::with_name("stats")
+            .about("Shows size, limit, hits, misses and evictions of the cache"))
+}
+
+
+pub fn run(arguments: &ArgMatches) -> Result<()> {
+    let cache = RenderCache::open()?;
+
+    match arguments.subcommand_name() {
+        Some("ls") => list(&cache),
+        Some("clear") => clear(&cache),
+        Some("stats") => print_stats(&cache),
+        _ => bail!("unsupported cache command"),
+    }
+}
+
+
+fn list(cache: &RenderCache) -> Result<()> {
+    let mut entries = cache.entries()?;
+    entries.sort_by_key(|entry| entry.last_used);
+
+    println!("{:<16}  {:>11}  {:>5}  {:>10}  region (min re, min im, width, height)", "key", "resolution", "iter", "size [kB]");
+
+    for entry in entries {
+        let (key, _) = read_entry(&entry.path)?;
+
+        println!("{:<16}  {:>11}  {:>5}  {:>10}  {}, {}, {}, {}",
+                 key.hash(),
+                 format!("{}x{}", key.width, key.height),
+                 key.max_iterations,
+                 entry.size / 1024,
+                 key.min_re,
+                 key.min_im,
+                 key.region_width,
+                 key.region_height);
+    }
+
+    Ok(())
+}
+
+
+fn clear(cache: &RenderCache) -> Result<()> {
+    let entries = cache.entries()?;
+
+    for entry in &entries {
+        fs::remove_file(&entry.path)?;
+    }
+
+    println!("removed {} entries from {}", entries.len(), cache.directory.display());
+
+    Ok(())
+}
+
+
+fn print_stats(cache: &RenderCache) -> Result<()> {
+    let entries = cache.entries()?;
+    let counters = cache.counters();
+    let total_size: u64 = entries.iter().map(|entry| entry.size).sum();
+
+    println!("directory: {}", cache.directory.display());
+    println!("entries:   {}", entries.len());
+    println!("size:      {:.1} MB of {:.1} MB", total_size as f64 / 1048576.0, cache.limit_bytes as f64 / 1048576.0);
+    println!("hits:      {}", counters.hits);
+    println!("misses:    {}", counters.misses);
+    println!("evictions: {}", counters.evictions);
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::complex::Complex64;
+    use rayon::prelude::*;
+
+    fn test_cache(name: &str, limit_bytes: u64) -> RenderCache {
+        let directory = env::temp_dir().join(format!("fractal_cache_test_{}", name));
+        let _ = fs::remove_dir_all(&directory);
+
+        RenderCache { directory, limit_bytes }
+    }
+
+    #[test]
+    fn hash_depends_on_region_and_iterations() {
+        // arrange
+        let region = Region::new_for_center(Complex64::new(-0.5, 0.0), 1.0);
+        let engine = SimpleMandelbrotEngine::new(false);
+        let key = CacheKey::new(&Mandelbrot::new(region, 64, 48, 100), &engine);
+
+        // act & assert
+        assert_eq!(key.hash(), CacheKey::new(&Mandelbrot::new(region, 64, 48, 100), &engine).hash());
+        assert_ne!(key.hash(), CacheKey::new(&Mandelbrot::new(region, 64, 48, 101), &engine).hash());
+        assert_ne!(key.hash(), CacheKey::new(&Mandelbrot::new(Region::new_for_center(Complex64::new(-0.5, 0.0), 0.5), 64, 48, 100), &engine).hash());
+    }
+
+    #[test]
+    fn hash_depends_on_exponential_map_center() {
+        // arrange
+        let mandelbrot = || Mandelbrot::new(Region::new_for_type(RegionType::Default), 64, 48, 100);
+        let engine = SimpleMandelbrotEngine::new(false);
+        let key = CacheKey::new(&mandelbrot(), &engine);
+
+        // act & assert
+        assert_ne!(key.hash(), CacheKey::new(&mandelbrot().with_exponential_map(Complex64::new(-0.5, 0.0)), &engine).hash());
+        assert_ne!(CacheKey::new(&mandelbrot().with_exponential_map(Complex64::new(-0.5, 0.0)), &engine).hash(),
+                   CacheKey::new(&mandelbrot().with_exponential_map(Complex64::new(-0.5, 0.1)), &engine).hash());
+    }
+
+    #[test]
+    fn hash_depends_on_engine_and_band_height() {
+        // arrange
+        let mandelbrot = Mandelbrot::new(Region::new_for_type(RegionType::Default), 64, 48, 100);
+        let key = CacheKey::new(&mandelbrot, &SimpleMandelbrotEngine::new(true));
+
+        // act & assert
+        assert_eq!(key.hash(), CacheKey::new(&mandelbrot, &SimpleMandelbrotEngine::new(false)).hash());
+        assert_ne!(key.hash(), CacheKey::new(&mandelbrot, &RecursiveMandelbrotEngine::new(false, 16)).hash());
+        assert_ne!(CacheKey::new(&mandelbrot, &RecursiveMandelbrotEngine::new(true, 16)).hash(),
+                   CacheKey::new(&mandelbrot, &RecursiveMandelbrotEngine::new(true, 24)).hash());
+    }
+
+    #[test]
+    fn calculate_returns_cached_iterations_on_second_call() {
+        // arrange
+        let cache = test_cache("hit", 1024 * 1024);
+        let mandelbrot = Mandelbrot::new(Region::new_for_type(RegionType::Default), 32, 24, 50);
+        let engine = SimpleMandelbrotEngine::new(false);
+
+        // act
+        let (calculated, _) = cache.calculate(&mandelbrot, &engine).unwrap();
+        let (cached, stats) = cache.calculate(&mandelbrot, &engine).unwrap();
+
+        // assert
+        assert_eq!(cached, calculated);
+        assert_eq!(stats.escape_time_calls, 0);
+        assert_eq!(cache.counters().hits, 1);
+        assert_eq!(cache.counters().misses, 1);
+    }
+
+    #[test]
+    fn store_beyond_limit_evicts_least_recently_used() {
+        // arrange
+        // room for two entries of 768 pixels and their headers
+        let cache = test_cache("eviction", 2 * (32 * 24 + 300));
+        let engine = SimpleMandelbrotEngine::new(false);
+        let first = Mandelbrot::new(Region::new_for_type(RegionType::Default), 32, 24, 10);
+        let second = Mandelbrot::new(Region::new_for_type(RegionType::Default), 32, 24, 20);
+        let third = Mandelbrot::new(Region::new_for_type(RegionType::Default), 32, 24, 30);
+
+        // act
+        cache.calculate(&first, &engine).unwrap();
+        File::options().write(true).open(cache.entry_path(&CacheKey::new(&first, &engine))).unwrap().set_modified(SystemTime::UNIX_EPOCH).unwrap();
+        cache.calculate(&second, &engine).unwrap();
+        cache.calculate(&third, &engine).unwrap();
+
+        // assert
+        assert_eq!(cache.entries().unwrap().len(), 2);
+        assert!(!cache.entry_path(&CacheKey::new(&first, &engine)).exists());
+        assert_eq!(cache.counters().evictions, 1);
+    }
+
+    #[test]
+    fn parallel_calculations_share_the_cache() {
+        // arrange
+        // room for a single entry, so the renders keep evicting each other's entries
+        let cache = test_cache("parallel", 32 * 24 + 300);
+        let engine = SimpleMandelbrotEngine::new(false);
+        let mandelbrots: Vec<_> = (0..32).map(|i| Mandelbrot::new(Region::new_for_type(RegionType::Default), 32, 24, 10 + i % 4)).collect();
+
+        // act
+        let results: Vec<_> = mandelbrots.par_iter().map(|mandelbrot| cache.calculate(mandelbrot, &engine)).collect();
+
+        // assert
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(cache.counters().hits + cache.counters().misses, 32);
+        assert!(fs::read_dir(&cache.directory).unwrap().all(|entry| entry.unwrap().path().extension().unwrap() != "partial"));
+    }
+}
@@ -0,0 +1,158 @@
+use num::complex::Complex64;
+
+use errors::*;
+use fractal::prelude::*;
+use super::parsed_engine;
+use text_art::CharacterGrid;
+
+
+pub const FORMULA: &str = "z^2+c";
+pub const PALETTE: &str = "gray";
+
+pub const SOFTWARE_KEYWORD: &str = "Software";
+pub const KEYWORD_PREFIX: &str = "fractal:";
+// Written for frames of keyframe animations, which cannot be re-rendered from the parameters
+const VIEW_KEYWORDS: &[&str] = &["rotation", "julia-constant", "palette-offset"];
+
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RenderParameters {
+    pub region: Region,
+    pub width: u32,
+    pub height: u32,
+    pub max_iterations: u8,
+    pub engine_type: String,
+    pub in_parallel: bool,
+    pub band_height: u32,
+}
+
+
+impl RenderParameters {
+    pub fn mandelbrot(&self) -> Mandelbrot {
+        Mandelbrot::new(self.region, self.width, self.height, self.max_iterations)
+    }
+
+
+    pub fn engine(&self) -> Result<Box<dyn MandelbrotEngine>> {
+        parsed_engine(&self.engine_type, self.in_parallel, &self.band_height.to_string())
+    }
+
+
+    // Renders the region into the cells of grid without distorting it, with rows per character rows of pixels per cell
+    pub fn for_character_grid(&self, grid: &CharacterGrid, rows_per_character: u32) -> RenderParameters {
+        let (width, height) = grid.image_size(rows_per_character);
+
+        RenderParameters { region: grid.fitted_region(self.region), width, height, ..self.clone() }
+    }
+
+
+    pub fn to_text_chunks(&self) -> Vec<(String, String)> {
+        let parameters = vec![
+            ("min-re", self.region.min_re().to_string()),
+            ("min-im", self.region.min_im().to_string()),
+            ("region-width", self.region.width().to_string()),
+            ("region-height", self.region.height().to_string()),
+            ("resolution", format!("{}x{}", self.width, self.height)),
+            ("formula", FORMULA.to_string()),
+            ("max-iterations", self.max_iterations.to_string()),
+            ("engine", self.engine_type.clone()),
+            ("parallel", self.in_parallel.to_string()),
+            ("band-height", self.band_height.to_string()),
+            ("palette", PALETTE.to_string()),
+        ];
+
+        let mut chunks = vec![(SOFTWARE_KEYWORD.to_string(), software())];
+        chunks.extend(parameters.into_iter().map(|(keyword, text)| (format!("{}{}", KEYWORD_PREFIX, keyword), text)));
+
+        chunks
+    }
+
+
+    pub fn from_text_chunks(chunks: &[(String, String)]) -> Result<RenderParameters> {
+        let text = |keyword: &str| -> Result<&str> {
+            let keyword = format!("{}{}", KEYWORD_PREFIX, keyword);
+
+            chunks.iter()
+                .find(|chunk| chunk.0 == keyword)
+                .map(|chunk| chunk.1.as_str())
+                .ok_or_else(|| format!("missing {} chunk", keyword).into())
+        };
+
+        if let Some(chunk) = chunks.iter().find(|chunk| chunk.0 == SOFTWARE_KEYWORD) {
+            if chunk.1 != software() {
+                warn!("image was created by {}, re-rendering with {}", chunk.1, software());
+            }
+        }
+
+        if text("formula")? != FORMULA {
+            bail!("unsupported formula");
+        }
+
+        if text("palette")? != PALETTE {
+            bail!("unsupported palette");
+        }
+
+        if let Some(keyword) = VIEW_KEYWORDS.iter().find(|keyword| text(keyword).is_ok()) {
+            bail!("re-rendering images with {} is not supported", keyword);
+        }
+
+        let min_re = text("min-re")?.parse::<f64>().chain_err(|| "invalid min-re")?;
+        let min_im = text("min-im")?.parse::<f64>().chain_err(|| "invalid min-im")?;
+        let region_width = text("region-width")?.parse::<f64>().chain_err(|| "invalid region width")?;
+        let region_height = text("region-height")?.parse::<f64>().chain_err(|| "invalid region height")?;
+        let (width, height) = super::parsed_resolution(text("resolution")?)?;
+
+        Ok(RenderParameters {
+            region: Region::new_for_corner(Complex64::new(min_re, min_im), region_width, region_height),
+            width,
+            height,
+            max_iterations: super::parsed_max_iterations(text("max-iterations")?)?,
+            engine_type: text("engine")?.to_string(),
+            in_parallel: text("parallel")?.parse::<bool>().chain_err(|| "invalid parallel flag")?,
+            band_height: text("band-height")?.parse::<u32>().chain_err(|| "invalid band height")?,
+        })
+    }
+}
+
+
+pub fn software() -> String {
+    format!("fractal {}", env!("CARGO_PKG_VERSION"))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_text_chunks_restores_parameters() {
+        // arrange
+        let parameters = RenderParameters {
+            region: Region::new_for_center(Complex64::new(-0.74548, 0.11669), 0.01276),
+            width: 320,
+            height: 200,
+            max_iterations: 100,
+            engine_type: "Recursive".to_string(),
+            in_parallel: true,
+            band_height: 32,
+        };
+
+        // act
+        let restored = RenderParameters::from_text_chunks(&parameters.to_text_chunks()).unwrap();
+
+        // assert
+        assert_eq!(restored, parameters);
+    }
+
+    #[test]
+    fn from_text_chunks_with_missing_chunks_fails() {
+        // arrange
+        let chunks = vec![("fractal:formula".to_string(), FORMULA.to_string())];
+
+        // act
+        let result = RenderParameters::from_text_chunks(&chunks);
+
+        // assert
+        assert!(result.is_err());
+    }
+}
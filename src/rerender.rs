@@ -0,0 +1,138 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use errors::*;
+use config::Config;
+use png_file;
+use render_cache::RenderCache;
+use render_parameters::RenderParameters;
+use super::{create_mandelbrot_file, parsed_resolution, parsed_max_iterations, parsed_region, parsed_output_format};
+
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("rerender")
+        .about("Re-renders an image from the parameters embedded in it, optionally overriding some of them")
+        .arg(Arg::with_name("image")
+            .help("PNG image created by fractal")
+            .required(true)
+            .value_name("IMAGE"))
+        .arg(Arg::with_name("type")
+            .short("t")
+            .long("type")
+            .conflicts_with("center-and-radius")
+            .help("Overrides the region by a region type or preset (see: fractal presets list)")
+            .takes_value(true)
+            .value_name("TYPE"))
+        .arg(Arg::with_name("center-and-radius")
+            .short("c")
+            .long("center-and-radius")
+            .conflicts_with("type")
+            .help("Overrides the region by a center and radius (format: <real>/<imag>@radius)")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("CENTER-AND-RADIUS"))
+        .arg(Arg::with_name("max-iterations")
+            .short("i")
+            .long("max-iterations")
+            .help("Overrides max iterations")
+            .takes_value(true)
+            .value_name("MAX-ITERATIONS"))
+        .arg(Arg::with_name("resolution")
+            .short("r")
+            .long("resolution")
+            .help("Overrides the resolution of the image (format: <width>x<height>)")
+            .takes_value(true)
+            .value_name("RESOLUTION"))
+        .arg(Arg::with_name("parallel")
+            .short("p")
+            .long("parallel")
+            .conflicts_with("serial")
+            .help("Overrides the calculation to be done in parallel"))
+        .arg(Arg::with_name("serial")
+            .long("serial")
+            .conflicts_with("parallel")
+            .help("Overrides the calculation to be done serially"))
+        .arg(Arg::with_name("band-height")
+            .short("b")
+            .long("band-height")
+            .help("Overrides number of rows per band")
+            .takes_value(true)
+            .value_name("BAND-HEIGHT"))
+        .arg(Arg::with_name("engine")
+            .short("e")
+            .long("engine")
+            .help("Overrides engine to use (values: Default, Recursive)")
+            .takes_value(true)
+            .value_name("ENGINE"))
+        .arg(Arg::with_name("output-filename")
+            .short("o")
+            .long("output-filename")
+            .help("Specifies the output file name of the image (- writes the image to stdout)")
+            .required(true)
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("OUTPUT-FILENAME"))
+        .arg(Arg::with_name("format")
+            .long("format")
+            .help("Specifies the output format instead of deriving it from the file name extension (values: png, png16, png16-rgb, tiff, pgm, ppm, exr)")
+            .takes_value(true)
+            .value_name("FORMAT"))
+        .arg(Arg::with_name("cache")
+            .long("cache")
+            .help("Reuses the iterations of an identical earlier render from the on-disk cache, or stores them there"))
+        .arg(Arg::with_name("stats")
+            .long("stats")
+            .help("Writes render statistics as JSON to the given file")
+            .takes_value(true)
+            .value_name("STATS-FILENAME"))
+}
+
+
+pub fn run(arguments: &ArgMatches, config: &Config) -> Result<()> {
+    let image_filename = arguments.value_of("image").unwrap();
+    let text_chunks = png_file::read_text_chunks(image_filename).chain_err(|| "reading image failed")?;
+    let mut parameters = RenderParameters::from_text_chunks(&text_chunks).chain_err(|| "reading render parameters from image failed")?;
+
+    apply_overrides(&mut parameters, arguments, config)?;
+
+    let output_filename = arguments.value_of("output-filename").unwrap();
+    let output_format = parsed_output_format(arguments.value_of("format"), output_filename).chain_err(|| "parsing output format failed")?;
+
+    let cache = if arguments.is_present("cache") { Some(RenderCache::open().chain_err(|| "opening render cache failed")?) } else { None };
+
+    create_mandelbrot_file(&parameters, output_filename, output_format, None, cache.as_ref(), arguments.value_of("stats"))
+}
+
+
+fn apply_overrides(parameters: &mut RenderParameters, arguments: &ArgMatches, config: &Config) -> Result<()> {
+    if arguments.is_present("type") || arguments.is_present("center-and-radius") {
+        parameters.region = parsed_region(config, arguments.value_of("type"), arguments.value_of("center-and-radius")).chain_err(|| "parsing region failed")?;
+    }
+
+    if let Some(resolution) = arguments.value_of("resolution") {
+        let (width, height) = parsed_resolution(resolution).chain_err(|| "parsing resolution failed")?;
+        parameters.width = width;
+        parameters.height = height;
+    }
+
+    if let Some(max_iterations) = arguments.value_of("max-iterations") {
+        parameters.max_iterations = parsed_max_iterations(max_iterations).chain_err(|| "parsing max iterations failed")?;
+    }
+
+    if let Some(engine_type) = arguments.value_of("engine") {
+        parameters.engine_type = engine_type.to_string();
+    }
+
+    if arguments.is_present("parallel") {
+        parameters.in_parallel = true;
+    }
+
+    if arguments.is_present("serial") {
+        parameters.in_parallel = false;
+    }
+
+    if let Some(band_height) = arguments.value_of("band-height") {
+        parameters.band_height = band_height.parse::<u32>().chain_err(|| "invalid band size")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,542 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use png;
+use serde_json;
+
+use errors::*;
+use config::Config;
+use fractal::prelude::*;
+use fractal::window::Window;
+use png_file;
+use render_parameters::RenderParameters;
+use tile_cache::LruCache;
+use tiles;
+use super::{elapsed_milliseconds, parsed_max_iterations, parsed_region, parsed_center_and_radius, parsed_engine};
+
+
+const TILE_SIZE: u32 = 256;
+// Beyond this zoom level the width of a level does not fit into u32 anymore
+const MAX_ZOOM: u32 = 23;
+// Rendering checks between bands of this height whether the client is still waiting
+const CANCELLATION_BAND_HEIGHT: u32 = 32;
+const MAX_RENDER_PIXELS: u64 = 4096 * 4096;
+// Connections are handled by a fixed number of workers, further connections wait in the backlog
+const WORKERS: usize = 16;
+const BACKLOG: usize = 64;
+// Clients which don't send their request in time are dropped, so they cannot hold on to a worker
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+// Limits the request line and headers, so a client cannot grow the memory of a worker by sending an endless line
+const MAX_REQUEST_SIZE: u64 = 8 * 1024;
+const VIEWER_PAGE: &str = include_str!("viewer.html");
+
+
+// Encoded PNG tiles by zoom level, x and y
+type TileCache = LruCache<(u32, u32, u32), Arc<Vec<u8>>>;
+
+
+struct Server {
+    region: Region,
+    max_iterations: u8,
+    engine_type: String,
+    tiles: Mutex<TileCache>,
+}
+
+
+// Describes the tile pyramid for the viewer
+#[derive(Debug, Serialize)]
+struct ServerInfo {
+    min_re: f64,
+    min_im: f64,
+    region_width: f64,
+    region_height: f64,
+    tile_size: u32,
+    max_zoom: u32,
+    max_iterations: u8,
+}
+
+
+struct Request {
+    path: String,
+    query: Vec<(String, String)>,
+}
+
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Arc<Vec<u8>>,
+}
+
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("serve")
+        .about("Serves tiles, renders and a map viewer over HTTP for interactive exploration")
+        .arg(Arg::with_name("port")
+            .long("port")
+            .help("Port to listen on")
+            .takes_value(true)
+            .value_name("PORT")
+            .default_value("8080"))
+        .arg(Arg::with_name("bind")
+            .long("bind")
+            .help("Address to listen on (use 0.0.0.0 to share the explorer on the network)")
+            .takes_value(true)
+            .value_name("ADDRESS")
+            .default_value("127.0.0.1"))
+        .arg(Arg::with_name("type")
+            .short("t")
+            .long("type")
+            .help("Sets the region of the top tile by a region type or preset (see: fractal presets list)")
+            .takes_value(true)
+            .value_name("TYPE")
+            .default_value("Default"))
+        .arg(Arg::with_name("center-and-radius")
+            .short("c")
+            .long("center-and-radius")
+            .conflicts_with("type")
+            .help("Sets the region of the top tile by a center and radius (format: <real>/<imag>@radius)")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("CENTER-AND-RADIUS"))
+        .arg(Arg::with_name("max-iterations")
+            .short("i")
+            .long("max-iterations")
+            .help("Sets max iterations (default: from the config, or 255)")
+            .takes_value(true)
+            .value_name("MAX-ITERATIONS"))
+        .arg(Arg::with_name("engine")
+            .short("e")
+            .long("engine")
+            .help("Choose engine to to use (values: Default, Recursive, default: from the config, or Default)")
+            .takes_value(true)
+            .value_name("ENGINE"))
+        .arg(Arg::with_name("cache-size")
+            .long("cache-size")
+            .help("Number of tiles kept in memory")
+            .takes_value(true)
+            .value_name("CACHE-SIZE")
+            .default_value("1024"))
+}
+
+
+pub fn run(arguments: &ArgMatches, config: &Config) -> Result<()> {
+    let port = arguments.value_of("port").unwrap().parse::<u16>().chain_err(|| "invalid port")?;
+    let cache_size = arguments.value_of("cache-size").unwrap().parse::<usize>().chain_err(|| "invalid cache size")?;
+    let engine_type = arguments.value_of("engine").unwrap_or(config.engine());
+    let max_iterations = match arguments.value_of("max-iterations") {
+        Some(max_iterations) => parsed_max_iterations(max_iterations).chain_err(|| "parsing max iterations failed")?,
+        None => config.max_iterations(),
+    };
+
+    // Fail early instead of on the first request
+    parsed_engine(engine_type, false, &CANCELLATION_BAND_HEIGHT.to_string()).chain_err(|| "parsing engine type failed")?;
+
+    let server = Arc::new(Server {
+        region: parsed_region(config, arguments.value_of("type"), arguments.value_of("center-and-radius")).chain_err(|| "parsing region failed")?,
+        max_iterations,
+        engine_type: engine_type.to_string(),
+        tiles: Mutex::new(LruCache::new(cache_size)),
+    });
+
+    let listener = TcpListener::bind((arguments.value_of("bind").unwrap(), port)).chain_err(|| "binding server socket failed")?;
+
+    println!("serving on http://{}/", listener.local_addr()?);
+
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(BACKLOG);
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    for _ in 0..WORKERS {
+        let server = server.clone();
+        let receiver = receiver.clone();
+
+        thread::spawn(move || {
+            loop {
+                let stream = match receiver.lock().unwrap().recv() {
+                    Ok(stream) => stream,
+                    Err(_) => return,
+                };
+
+                handle_connection(&server, stream);
+            }
+        });
+    }
+
+    for stream in listener.incoming() {
+        match stream.and_then(|stream| stream.set_read_timeout(Some(READ_TIMEOUT)).map(|_| stream)) {
+            Ok(stream) => sender.send(stream).chain_err(|| "connection workers stopped")?,
+            Err(e) => warn!("accepting connection failed: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+
+fn handle_connection(server: &Server, mut stream: TcpStream) {
+    let start = Instant::now();
+
+    let request = match read_request(&stream) {
+        Ok(Ok(request)) => request,
+        Ok(Err(reason)) => {
+            debug!("invalid request: {}", reason);
+
+            if let Err(e) = write_response(&mut stream, &Response::text("400 Bad Request", reason)) {
+                debug!("writing response failed: {}", e);
+            }
+
+            return;
+        },
+        Err(e) => {
+            debug!("reading request failed: {}", e);
+            return;
+        },
+    };
+
+    let response = match server.respond(&request, &stream) {
+        Ok(Some(response)) => response,
+        Ok(None) => {
+            debug!(event = "request_cancelled", path = request.path.as_str(); "request for {} cancelled", request.path);
+            return;
+        },
+        Err(e) => {
+            warn!(event = "request_failed", path = request.path.as_str(); "request for {} failed: {}", request.path, e);
+            Response::text("500 Internal Server Error", &e.to_string())
+        },
+    };
+
+    if let Err(e) = write_response(&mut stream, &response) {
+        debug!("writing response failed: {}", e);
+    }
+
+    info!(event = "request", path = request.path.as_str(), status = response.status, elapsed_ms = elapsed_milliseconds(start);
+          "{} {}", response.status, request.path);
+}
+
+
+impl Server {
+    // Returns no response if the client went away while rendering, invalid requests get a response with status 400
+    // while errors are internal errors
+    fn respond(&self, request: &Request, stream: &TcpStream) -> Result<Option<Response>> {
+        let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+
+        match segments.as_slice() {
+            [""] | ["index.html"] => Ok(Some(Response::new("200 OK", "text/html; charset=utf-8", VIEWER_PAGE.as_bytes().to_vec()))),
+            ["info"] => Ok(Some(self.info()?)),
+            ["render"] => match self.parsed_render_parameters(request) {
+                Ok(parameters) => self.render(&parameters, stream),
+                Err(e) => Ok(Some(Response::text("400 Bad Request", &e.to_string()))),
+            },
+            ["tiles", z, x, y] if y.ends_with(".png") => match parsed_tile_coordinates(z, x, y) {
+                Ok((z, x, y)) => self.tile(z, x, y, stream),
+                Err(e) => Ok(Some(Response::text("400 Bad Request", &e.to_string()))),
+            },
+            _ => Ok(Some(Response::text("404 Not Found", "not found"))),
+        }
+    }
+
+
+    fn info(&self) -> Result<Response> {
+        let info = ServerInfo {
+            min_re: self.region.min_re(),
+            min_im: self.region.min_im(),
+            region_width: self.region.width(),
+            region_height: self.region.height(),
+            tile_size: TILE_SIZE,
+            max_zoom: MAX_ZOOM,
+            max_iterations: self.max_iterations,
+        };
+
+        Ok(Response::new("200 OK", "application/json", serde_json::to_vec(&info)?))
+    }
+
+
+    fn tile(&self, z: u32, x: u32, y: u32, stream: &TcpStream) -> Result<Option<Response>> {
+        if z > MAX_ZOOM {
+            return Ok(Some(Response::text("404 Not Found", "zoom level out of range")));
+        }
+
+        let level = tiles::xyz_level(self.region, TILE_SIZE, z);
+
+        if x >= level.width / TILE_SIZE || y >= level.height / TILE_SIZE {
+            return Ok(Some(Response::text("404 Not Found", "tile out of range")));
+        }
+
+        if let Some(png) = self.tiles.lock().unwrap().get(&(z, x, y)) {
+            return Ok(Some(Response { status: "200 OK", content_type: "image/png", body: png }));
+        }
+
+        let level_mandelbrot = Mandelbrot::new(level.region, level.width, level.height, self.max_iterations);
+        let tile_region = level_mandelbrot.region_for_window(&Window::new(x * TILE_SIZE, y * TILE_SIZE, TILE_SIZE, TILE_SIZE));
+        let mandelbrot = Mandelbrot::new(tile_region, TILE_SIZE, TILE_SIZE, self.max_iterations);
+
+        // Browsers request many tiles at once, so every tile is calculated serially
+        let engine = parsed_engine(&self.engine_type, false, &CANCELLATION_BAND_HEIGHT.to_string())?;
+
+        let pixels = match calculate_while_connected(&mandelbrot, &*engine, stream) {
+            Some(pixels) => pixels,
+            None => return Ok(None),
+        };
+
+        let mut png: Vec<u8> = Vec::new();
+        png_file::write(&mut png, TILE_SIZE, TILE_SIZE, png::ColorType::Grayscale, png::BitDepth::Eight, &pixels, &[])?;
+        let png = Arc::new(png);
+
+        self.tiles.lock().unwrap().insert((z, x, y), png.clone());
+
+        Ok(Some(Response { status: "200 OK", content_type: "image/png", body: png }))
+    }
+
+
+    fn parsed_render_parameters(&self, request: &Request) -> Result<RenderParameters> {
+        let center = request.parameter("center").ok_or("center is required (format: <real>,<imag>)")?;
+        let radius = request.parameter("radius").ok_or("radius is required")?;
+        let width = request.parameter("w").unwrap_or("1024").parse::<u32>().chain_err(|| "invalid width")?;
+        let height = request.parameter("h").unwrap_or("768").parse::<u32>().chain_err(|| "invalid height")?;
+
+        if width == 0 || height == 0 || (width as u64) * (height as u64) > MAX_RENDER_PIXELS {
+            bail!("image size has to be between 1 and {} pixels", MAX_RENDER_PIXELS);
+        }
+
+        let max_iterations = match request.parameter("i") {
+            Some(max_iterations) => parsed_max_iterations(max_iterations)?,
+            None => self.max_iterations,
+        };
+
+        Ok(RenderParameters {
+            region: parsed_center_and_radius(&format!("{}@{}", center.replace(',', "/"), radius))?,
+            width,
+            height,
+            max_iterations,
+            engine_type: self.engine_type.clone(),
+            in_parallel: true,
+            band_height: CANCELLATION_BAND_HEIGHT,
+        })
+    }
+
+
+    fn render(&self, parameters: &RenderParameters, stream: &TcpStream) -> Result<Option<Response>> {
+        let pixels = match calculate_while_connected(&parameters.mandelbrot(), &*parameters.engine()?, stream) {
+            Some(pixels) => pixels,
+            None => return Ok(None),
+        };
+
+        let mut png: Vec<u8> = Vec::new();
+        png_file::write(&mut png, parameters.width, parameters.height, png::ColorType::Grayscale, png::BitDepth::Eight, &pixels, &parameters.to_text_chunks())?;
+
+        Ok(Some(Response::new("200 OK", "image/png", png)))
+    }
+}
+
+
+fn parsed_tile_coordinates(z: &str, x: &str, y: &str) -> Result<(u32, u32, u32)> {
+    let z = z.parse::<u32>().chain_err(|| "invalid zoom level")?;
+    let x = x.parse::<u32>().chain_err(|| "invalid tile column")?;
+    let y = y.trim_end_matches(".png").parse::<u32>().chain_err(|| "invalid tile row")?;
+
+    Ok((z, x, y))
+}
+
+
+impl Request {
+    fn parameter(&self, name: &str) -> Option<&str> {
+        self.query.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+
+impl Response {
+    fn new(status: &'static str, content_type: &'static str, body: Vec<u8>) -> Response {
+        Response { status, content_type, body: Arc::new(body) }
+    }
+
+
+    fn text(status: &'static str, text: &str) -> Response {
+        Response::new(status, "text/plain; charset=utf-8", text.as_bytes().to_vec())
+    }
+}
+
+
+// Calculates band by band and gives up as soon as the client closed the connection
+fn calculate_while_connected(mandelbrot: &Mandelbrot, engine: &dyn MandelbrotEngine, stream: &TcpStream) -> Option<Vec<u8>> {
+    let mut pixels: Vec<u8> = Vec::with_capacity((mandelbrot.width as usize) * (mandelbrot.height as usize));
+
+    let result = mandelbrot.calculate_bands(engine, CANCELLATION_BAND_HEIGHT, |band| {
+        if connection_closed(stream) {
+            return Err(());
+        }
+
+        pixels.extend_from_slice(band);
+        Ok(())
+    });
+
+    result.ok().map(|_| pixels)
+}
+
+
+fn connection_closed(stream: &TcpStream) -> bool {
+    let mut buffer = [0u8; 1];
+
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+
+    let closed = match stream.peek(&mut buffer) {
+        Ok(0) => true,
+        Ok(_) => false,
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => false,
+        Err(_) => true,
+    };
+
+    let _ = stream.set_nonblocking(false);
+
+    closed
+}
+
+
+// Fails on I/O errors, requests which cannot be answered are returned as the reason for a response with status 400
+fn read_request<R: Read>(stream: R) -> Result<::std::result::Result<Request, &'static str>> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_SIZE));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Headers are not needed, but have to be consumed
+    let mut header = request_line.clone();
+
+    while !header.trim().is_empty() {
+        if !header.ends_with('\n') {
+            return Ok(Err(if reader.get_ref().limit() == 0 { "request too large" } else { "incomplete request" }));
+        }
+
+        header.clear();
+
+        if reader.read_line(&mut header)? == 0 {
+            break;
+        }
+    }
+
+    let tokens: Vec<&str> = request_line.split_whitespace().collect();
+
+    if tokens.len() != 3 || tokens[0] != "GET" {
+        return Ok(Err("unsupported request"));
+    }
+
+    Ok(Ok(parsed_request_target(tokens[1])))
+}
+
+
+fn parsed_request_target(target: &str) -> Request {
+    let mut parts = target.splitn(2, '?');
+    let path = percent_decoded(parts.next().unwrap_or(""));
+
+    let query = parts.next()
+        .unwrap_or("")
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let mut pair = pair.splitn(2, '=');
+            let key = percent_decoded(pair.next().unwrap_or(""));
+            let value = percent_decoded(pair.next().unwrap_or(""));
+
+            (key, value)
+        })
+        .collect();
+
+    Request { path, query }
+}
+
+
+fn percent_decoded(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex_value = if bytes[i] == b'%' && i + 2 < bytes.len() {
+            ::std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+
+        match (bytes[i], hex_value) {
+            (_, Some(value)) => {
+                decoded.push(value);
+                i += 3;
+            },
+            (b'+', None) => {
+                decoded.push(b' ');
+                i += 1;
+            },
+            (byte, None) => {
+                decoded.push(byte);
+                i += 1;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> io::Result<()> {
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+           response.status,
+           response.content_type,
+           response.body.len())?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsed_request_target_decodes_path_and_query() {
+        // act
+        let request = parsed_request_target("/render?center=-0.74548%2C0.11669&radius=0.01&w=");
+
+        // assert
+        assert_eq!(request.path, "/render");
+        assert_eq!(request.parameter("center"), Some("-0.74548,0.11669"));
+        assert_eq!(request.parameter("radius"), Some("0.01"));
+        assert_eq!(request.parameter("w"), Some(""));
+        assert_eq!(request.parameter("h"), None);
+    }
+
+    #[test]
+    fn read_request_consumes_headers_and_rejects_oversized_requests() {
+        // arrange
+        let request = b"GET /tiles/0/0/0.png HTTP/1.1\r\nHost: localhost\r\n\r\n".to_vec();
+        let endless_line = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_REQUEST_SIZE as usize));
+
+        // act
+        let read = read_request(&request[..]).unwrap();
+        let oversized = read_request(endless_line.as_bytes()).unwrap();
+
+        // assert
+        assert_eq!(read.unwrap().path, "/tiles/0/0/0.png");
+        assert_eq!(oversized.err(), Some("request too large"));
+    }
+
+    #[test]
+    fn parsed_tile_coordinates_strips_extension_and_rejects_invalid_numbers() {
+        // act & assert
+        assert_eq!(parsed_tile_coordinates("3", "5", "2.png").unwrap(), (3, 5, 2));
+        assert!(parsed_tile_coordinates("3", "-5", "2.png").is_err());
+    }
+
+    #[test]
+    fn percent_decoded_keeps_incomplete_escapes() {
+        // act & assert
+        assert_eq!(percent_decoded("a+b%2Fc%2"), "a b/c%2");
+    }
+}
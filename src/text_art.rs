@@ -0,0 +1,190 @@
+use std::env;
+use std::fmt::Write;
+
+use crossterm::terminal;
+use num::complex::Complex64;
+
+use fractal::prelude::*;
+
+
+// Used when the terminal size is unknown, i.e. without a terminal
+const DEFAULT_COLUMNS: u32 = 80;
+const DEFAULT_ROWS: u32 = 24;
+// Character cells are about twice as high as wide
+const DEFAULT_CELL_ASPECT_RATIO: f64 = 2.0;
+// From dark to bright
+const DENSITY_RAMP: &[u8] = b" .:-=+*#%@";
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum AnsiColors {
+    Palette256,
+    TrueColor,
+}
+
+
+impl AnsiColors {
+    // Terminals announce 24 bit color support in COLORTERM
+    pub fn detected() -> AnsiColors {
+        match env::var("COLORTERM") {
+            Ok(ref colorterm) if colorterm == "truecolor" || colorterm == "24bit" => AnsiColors::TrueColor,
+            _ => AnsiColors::Palette256,
+        }
+    }
+
+
+    fn escape(&self, layer: u8, level: u8) -> String {
+        match *self {
+            AnsiColors::Palette256 => format!("\x1b[{};5;{}m", layer, palette_256_index(level)),
+            AnsiColors::TrueColor => format!("\x1b[{0};2;{1};{1};{1}m", layer, level),
+        }
+    }
+}
+
+
+// The characters text is rendered into, cell aspect ratio is the height of a cell divided by its width
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct CharacterGrid {
+    pub columns: u32,
+    pub rows: u32,
+    pub cell_aspect_ratio: f64,
+}
+
+
+impl CharacterGrid {
+    pub fn new(columns: u32, rows: u32) -> CharacterGrid {
+        CharacterGrid { columns, rows, cell_aspect_ratio: DEFAULT_CELL_ASPECT_RATIO }
+    }
+
+
+    // The terminal size leaving one row for the prompt
+    pub fn of_terminal() -> CharacterGrid {
+        let size = terminal::window_size()
+            .or_else(|_| terminal::size().map(|(columns, rows)| terminal::WindowSize { rows, columns, width: 0, height: 0 }));
+
+        match size {
+            Ok(ref size) if size.columns > 0 && size.rows > 1 => {
+                let cell_aspect_ratio = if size.width > 0 && size.height > 0 {
+                    (size.height as f64 / size.rows as f64) / (size.width as f64 / size.columns as f64)
+                } else {
+                    DEFAULT_CELL_ASPECT_RATIO
+                };
+
+                CharacterGrid { columns: size.columns as u32, rows: size.rows as u32 - 1, cell_aspect_ratio }
+            },
+            _ => CharacterGrid::new(DEFAULT_COLUMNS, DEFAULT_ROWS),
+        }
+    }
+
+
+    pub fn image_size(&self, rows_per_character: u32) -> (u32, u32) {
+        (self.columns, self.rows * rows_per_character)
+    }
+
+
+    // The smallest region around the center of region containing it, which isn't distorted by the cells
+    pub fn fitted_region(&self, region: Region) -> Region {
+        let width = self.columns as f64;
+        let height = self.rows as f64 * self.cell_aspect_ratio;
+        let scale = (region.width() / width).max(region.height() / height);
+        let center = Complex64::new(region.min_re() + region.width() / 2.0, region.min_im() + region.height() / 2.0);
+
+        Region::new_for_corner(center - Complex64::new(width, height) * scale / 2.0, width * scale, height * scale)
+    }
+}
+
+
+// One character per pixel, brighter pixels get denser characters
+pub fn ascii_lines(pixels: &[u8], width: usize) -> Vec<String> {
+    pixels.chunks(width)
+        .map(|row| {
+            row.iter()
+                .map(|&level| DENSITY_RAMP[(level as usize * (DENSITY_RAMP.len() - 1) + 127) / 255] as char)
+                .collect()
+        })
+        .collect()
+}
+
+
+// One line of upper half blocks per two rows of pixels: the foreground is the upper pixel, the background the lower one
+pub fn half_block_lines(pixels: &[u8], width: usize, height: usize, colors: AnsiColors) -> Vec<String> {
+    (0..height).step_by(2)
+        .map(|y| {
+            let mut line = String::new();
+            let mut levels = None;
+
+            for x in 0..width {
+                let upper = pixels[y * width + x];
+                let lower = if y + 1 < height { pixels[(y + 1) * width + x] } else { 0 };
+
+                if levels != Some((upper, lower)) {
+                    write!(line, "{}{}", colors.escape(38, upper), colors.escape(48, lower)).unwrap();
+                    levels = Some((upper, lower));
+                }
+
+                line.push('\u{2580}');
+            }
+
+            line.push_str("\x1b[0m");
+            line
+        })
+        .collect()
+}
+
+
+// Nearest of black (16), the gray ramp (232 to 255 for levels 8 to 238) and white (231)
+fn palette_256_index(level: u8) -> u8 {
+    match level {
+        0..=3 => 16,
+        247..=255 => 231,
+        _ => 232 + ((level - 3) / 10).min(23),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fitted_region_widens_square_region_for_cells_twice_as_high_as_wide() {
+        // arrange
+        let grid = CharacterGrid::new(80, 20);
+
+        // act
+        let region = grid.fitted_region(Region::new(-2.0, 2.0, -2.0, 2.0));
+
+        // assert
+        assert_eq!(region, Region::new(-4.0, 4.0, -2.0, 2.0));
+    }
+
+    #[test]
+    fn ascii_lines_use_density_ramp() {
+        // act
+        let lines = ascii_lines(&[0, 28, 128, 255, 255, 0], 3);
+
+        // assert
+        assert_eq!(lines, vec!(" .+".to_string(), "@@ ".to_string()));
+    }
+
+    #[test]
+    fn half_block_lines_pair_rows_and_skip_repeated_colors() {
+        // act
+        let lines = half_block_lines(&[10, 10, 20, 20, 30, 40], 2, 3, AnsiColors::TrueColor);
+
+        // assert
+        assert_eq!(lines, vec!(
+            "\x1b[38;2;10;10;10m\x1b[48;2;20;20;20m\u{2580}\u{2580}\x1b[0m".to_string(),
+            "\x1b[38;2;30;30;30m\x1b[48;2;0;0;0m\u{2580}\x1b[38;2;40;40;40m\x1b[48;2;0;0;0m\u{2580}\x1b[0m".to_string()));
+    }
+
+    #[test]
+    fn palette_256_index_picks_nearest_gray() {
+        // act & assert
+        assert_eq!(palette_256_index(0), 16);
+        assert_eq!(palette_256_index(8), 232);
+        assert_eq!(palette_256_index(128), 244);
+        assert_eq!(palette_256_index(238), 255);
+        assert_eq!(palette_256_index(255), 231);
+    }
+}
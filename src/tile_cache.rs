@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+
+// Keeps the most recently used entries up to a fixed number of entries
+pub struct LruCache<K, V> {
+    capacity: usize,
+    entries: HashMap<K, (V, u64)>,
+    clock: u64,
+}
+
+
+impl<K: Hash + Eq + Clone, V: Clone> LruCache<K, V> {
+    pub fn new(capacity: usize) -> LruCache<K, V> {
+        LruCache { capacity, entries: HashMap::new(), clock: 0 }
+    }
+
+
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        self.clock += 1;
+        let clock = self.clock;
+
+        self.entries.get_mut(key).map(|entry| {
+            entry.1 = clock;
+            entry.0.clone()
+        })
+    }
+
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            let least_recently_used = self.entries.iter()
+                .min_by_key(|&(_, &(_, last_used))| last_used)
+                .map(|(key, _)| key.clone());
+
+            if let Some(least_recently_used) = least_recently_used {
+                self.entries.remove(&least_recently_used);
+            }
+        }
+
+        self.clock += 1;
+        self.entries.insert(key, (value, self.clock));
+    }
+
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_beyond_capacity_evicts_least_recently_used() {
+        // arrange
+        let mut cache: LruCache<u32, &str> = LruCache::new(2);
+        cache.insert(1, "one");
+        cache.insert(2, "two");
+        cache.get(&1);
+
+        // act
+        cache.insert(3, "three");
+
+        // assert
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&1), Some("one"));
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&3), Some("three"));
+    }
+
+    #[test]
+    fn insert_existing_key_replaces_value() {
+        // arrange
+        let mut cache: LruCache<u32, &str> = LruCache::new(1);
+        cache.insert(1, "one");
+
+        // act
+        cache.insert(1, "uno");
+
+        // assert
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&1), Some("uno"));
+    }
+
+    #[test]
+    fn cache_without_capacity_stores_nothing() {
+        // arrange
+        let mut cache: LruCache<u32, &str> = LruCache::new(0);
+
+        // act
+        cache.insert(1, "one");
+
+        // assert
+        assert_eq!(cache.get(&1), None);
+    }
+}
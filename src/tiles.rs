@@ -0,0 +1,354 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use num::complex::Complex64;
+use png;
+use rayon::prelude::*;
+
+use errors::*;
+use config::Config;
+use fractal::prelude::*;
+use fractal::window::Window;
+use png_file;
+use super::{parsed_max_iterations, parsed_region, parsed_engine};
+
+
+const DZI_NAME: &str = "tiles";
+
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum TileLayout {
+    Dzi,
+    Xyz,
+}
+
+
+// A zoom level of the pyramid, which is rendered natively at its own resolution
+#[derive(Debug, PartialEq, Clone)]
+pub struct Level {
+    pub z: u32,
+    pub region: Region,
+    pub width: u32,
+    pub height: u32,
+}
+
+
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub window: Window,
+}
+
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("tiles")
+        .about("Renders a pyramid of image tiles for deep zoom viewers")
+        .arg(Arg::with_name("type")
+            .short("t")
+            .long("type")
+            .help("Sets a region type or preset (see: fractal presets list)")
+            .takes_value(true)
+            .value_name("TYPE")
+            .default_value("Default"))
+        .arg(Arg::with_name("center-and-radius")
+            .short("c")
+            .long("center-and-radius")
+            .conflicts_with("type")
+            .help("Specifies the center and radius of the region (format: <real>/<imag>@radius)")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .value_name("CENTER-AND-RADIUS"))
+        .arg(Arg::with_name("max-iterations")
+            .short("i")
+            .long("max-iterations")
+            .help("Sets max iterations (default: from the config, or 255)")
+            .takes_value(true)
+            .value_name("MAX-ITERATIONS"))
+        .arg(Arg::with_name("levels")
+            .short("l")
+            .long("levels")
+            .help("Number of zoom levels below the top level, each one doubling the resolution")
+            .takes_value(true)
+            .value_name("LEVELS")
+            .default_value("4"))
+        .arg(Arg::with_name("layout")
+            .long("layout")
+            .help("Layout of the tiles (values: xyz for <z>/<x>/<y>.png, dzi for Deep Zoom)")
+            .takes_value(true)
+            .value_name("LAYOUT")
+            .default_value("xyz"))
+        .arg(Arg::with_name("tile-size")
+            .long("tile-size")
+            .help("Width and height of the tiles in pixels")
+            .takes_value(true)
+            .value_name("TILE-SIZE")
+            .default_value("256"))
+        .arg(Arg::with_name("parallel")
+            .short("p")
+            .long("parallel")
+            .help("If specified the tiles are rendered in parallel"))
+        .arg(Arg::with_name("engine")
+            .short("e")
+            .long("engine")
+            .help("Choose engine to to use (values: Default, Recursive, default: from the config, or Default)")
+            .takes_value(true)
+            .value_name("ENGINE"))
+        .arg(Arg::with_name("output-directory")
+            .short("o")
+            .long("output-directory")
+            .help("Specifies the directory the tiles are written to, existing tiles are kept")
+            .required(true)
+            .takes_value(true)
+            .value_name("OUTPUT-DIRECTORY"))
+}
+
+
+pub fn run(arguments: &ArgMatches, config: &Config) -> Result<()> {
+    let region = parsed_region(config, arguments.value_of("type"), arguments.value_of("center-and-radius")).chain_err(|| "parsing region failed")?;
+    let max_iterations = match arguments.value_of("max-iterations") {
+        Some(max_iterations) => parsed_max_iterations(max_iterations).chain_err(|| "parsing max iterations failed")?,
+        None => config.max_iterations(),
+    };
+    let levels = arguments.value_of("levels").unwrap().parse::<u32>().chain_err(|| "invalid number of levels")?;
+    let layout = parsed_layout(arguments.value_of("layout").unwrap()).chain_err(|| "parsing tile layout failed")?;
+    let tile_size = arguments.value_of("tile-size").unwrap().parse::<u32>().chain_err(|| "invalid tile size")?;
+    let in_parallel = arguments.is_present("parallel");
+    let output_directory = Path::new(arguments.value_of("output-directory").unwrap());
+
+    if tile_size == 0 || levels > 20 {
+        bail!("tile size has to be positive and at most 20 levels are supported");
+    }
+
+    verify_full_resolution(region, tile_size, levels)?;
+
+    // Tiles are distributed over the threads, every tile itself is calculated serially
+    let engine = parsed_engine(arguments.value_of("engine").unwrap_or(config.engine()), false, &tile_size.to_string()).chain_err(|| "parsing engine type failed")?;
+
+    fs::create_dir_all(output_directory)?;
+
+    let pyramid = match layout {
+        TileLayout::Xyz => xyz_levels(region, tile_size, levels),
+        TileLayout::Dzi => {
+            let levels = dzi_levels(region, tile_size, levels);
+            let top_level = &levels[levels.len() - 1];
+            write_dzi_descriptor(&output_directory.join(format!("{}.dzi", DZI_NAME)), top_level.width, top_level.height, tile_size)?;
+
+            levels
+        },
+    };
+
+    for level in &pyramid {
+        let mandelbrot = Mandelbrot::new(level.region, level.width, level.height, max_iterations);
+        let tiles = tiles_of_level(level, tile_size);
+
+        let render = |tile: &Tile| -> Result<bool> {
+            let path = tile_path(output_directory, layout, level.z, tile);
+
+            // Tiles of an interrupted export are kept, so rendering can be resumed
+            if path.exists() {
+                return Ok(false);
+            }
+
+            let (pixels, _) = mandelbrot.calculate_window(&*engine, &tile.window);
+            write_tile(&path, tile.window.width, tile.window.height, &pixels)?;
+
+            Ok(true)
+        };
+
+        let rendered = if in_parallel {
+            tiles.par_iter().map(render).collect::<Result<Vec<bool>>>()?
+        } else {
+            tiles.iter().map(render).collect::<Result<Vec<bool>>>()?
+        };
+        let rendered_count = rendered.iter().filter(|&&rendered| rendered).count();
+
+        info!(event = "level_finished", level = level.z, tiles = tiles.len(), rendered = rendered_count, skipped = tiles.len() - rendered_count;
+              "level {} finished ({} of {} tiles rendered)", level.z, rendered_count, tiles.len());
+    }
+
+    Ok(())
+}
+
+
+fn parsed_layout(layout: &str) -> Result<TileLayout> {
+    match layout {
+        "xyz" => Ok(TileLayout::Xyz),
+        "dzi" => Ok(TileLayout::Dzi),
+        _ => bail!("unsupported tile layout")
+    }
+}
+
+
+// The deepest level is tile size times 2^levels pixels wide (and the XYZ layout rounds its height up to full tiles),
+// which has to fit into u32
+fn verify_full_resolution(region: Region, tile_size: u32, levels: u32) -> Result<()> {
+    let full_width = 1u32.checked_shl(levels)
+        .and_then(|scale| tile_size.checked_mul(scale))
+        .ok_or("tile size and number of levels exceed the maximum resolution")?;
+    let full_height = (full_width as f64) * region.height() / region.width() + tile_size as f64;
+
+    if full_height > u32::MAX as f64 {
+        bail!("tile size and number of levels exceed the maximum resolution");
+    }
+
+    Ok(())
+}
+
+
+pub fn xyz_levels(region: Region, tile_size: u32, levels: u32) -> Vec<Level> {
+    (0..levels + 1)
+        .map(|z| xyz_level(region, tile_size, z))
+        .collect()
+}
+
+
+// Level z is 2^z tiles wide; the region is extended downwards, so all tiles have the full tile size
+pub fn xyz_level(region: Region, tile_size: u32, z: u32) -> Level {
+    let width = tile_size << z;
+    let rows = ((width as f64) * region.height() / region.width() / (tile_size as f64)).ceil().max(1.0) as u32;
+    let height = rows * tile_size;
+    let pixel_size = region.width() / (width as f64);
+
+    Level {
+        z,
+        region: Region::new_for_corner(Complex64::new(region.min_re(), region.min_im()), region.width(), (height as f64) * pixel_size),
+        width,
+        height,
+    }
+}
+
+
+// Deep Zoom levels halve the full resolution down to a single pixel (level 0), edge tiles are cropped
+pub fn dzi_levels(region: Region, tile_size: u32, levels: u32) -> Vec<Level> {
+    let full_width = tile_size << levels;
+    let full_height = (((full_width as f64) * region.height() / region.width()).round() as u32).max(1);
+    let max_level = 32 - (full_width.max(full_height) - 1).leading_zeros();
+
+    (0..max_level + 1)
+        .map(|z| {
+            let divisor = 1u64 << (max_level - z);
+
+            Level {
+                z,
+                region,
+                width: (full_width as u64).div_ceil(divisor) as u32,
+                height: (full_height as u64).div_ceil(divisor) as u32,
+            }
+        })
+        .collect()
+}
+
+
+pub fn tiles_of_level(level: &Level, tile_size: u32) -> Vec<Tile> {
+    let columns = level.width.div_ceil(tile_size);
+    let rows = level.height.div_ceil(tile_size);
+
+    let mut tiles: Vec<Tile> = Vec::new();
+
+    for y in 0..rows {
+        for x in 0..columns {
+            let min_x = x * tile_size;
+            let min_y = y * tile_size;
+
+            tiles.push(Tile {
+                x,
+                y,
+                window: Window::new(min_x, min_y, tile_size.min(level.width - min_x), tile_size.min(level.height - min_y)),
+            });
+        }
+    }
+
+    tiles
+}
+
+
+pub fn tile_path(output_directory: &Path, layout: TileLayout, z: u32, tile: &Tile) -> PathBuf {
+    match layout {
+        TileLayout::Xyz => output_directory.join(z.to_string()).join(tile.x.to_string()).join(format!("{}.png", tile.y)),
+        TileLayout::Dzi => output_directory.join(format!("{}_files", DZI_NAME)).join(z.to_string()).join(format!("{}_{}.png", tile.x, tile.y)),
+    }
+}
+
+
+// Tiles are written to a temporary file first, so an interrupted export never leaves a truncated tile behind
+fn write_tile(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+
+    let temporary_path = path.with_extension("png.partial");
+    png_file::write(BufWriter::new(File::create(&temporary_path)?), width, height, png::ColorType::Grayscale, png::BitDepth::Eight, pixels, &[])?;
+    fs::rename(&temporary_path, path)?;
+
+    Ok(())
+}
+
+
+fn write_dzi_descriptor(path: &Path, width: u32, height: u32, tile_size: u32) -> Result<()> {
+    let mut output = File::create(path)?;
+
+    writeln!(output, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(output, r#"<Image xmlns="http://schemas.microsoft.com/deepzoom/2008" Format="png" Overlap="0" TileSize="{}">"#, tile_size)?;
+    writeln!(output, r#"  <Size Width="{}" Height="{}"/>"#, width, height)?;
+    writeln!(output, "</Image>")?;
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xyz_levels_double_resolution_and_keep_full_tiles() {
+        // act
+        let levels = xyz_levels(Region::new(-2.0, 1.0, -1.0, 1.0), 256, 2);
+
+        // assert
+        assert_eq!(levels.len(), 3);
+        assert_eq!((levels[0].width, levels[0].height), (256, 256));
+        assert_eq!((levels[2].width, levels[2].height), (1024, 768));
+        assert_eq!(levels[2].region.height(), 2.25);
+    }
+
+    #[test]
+    fn dzi_levels_go_down_to_single_pixel() {
+        // act
+        let levels = dzi_levels(Region::new(-2.0, 1.0, -1.0, 1.0), 256, 2);
+
+        // assert
+        assert_eq!(levels.len(), 11);
+        assert_eq!((levels[0].width, levels[0].height), (1, 1));
+        assert_eq!((levels[10].width, levels[10].height), (1024, 683));
+        assert_eq!((levels[9].width, levels[9].height), (512, 342));
+    }
+
+    #[test]
+    fn tiles_of_level_crops_edge_tiles() {
+        // arrange
+        let level = Level { z: 1, region: Region::new(-2.0, 1.0, -1.0, 1.0), width: 300, height: 256 };
+
+        // act
+        let tiles = tiles_of_level(&level, 256);
+
+        // assert
+        assert_eq!(tiles.len(), 2);
+        assert_eq!(tiles[1].window, Window::new(256, 0, 44, 256));
+        assert_eq!(tile_path(Path::new("out"), TileLayout::Xyz, 1, &tiles[1]), PathBuf::from("out/1/1/0.png"));
+        assert_eq!(tile_path(Path::new("out"), TileLayout::Dzi, 1, &tiles[1]), PathBuf::from("out/tiles_files/1/1_0.png"));
+    }
+
+    #[test]
+    fn verify_full_resolution_rejects_levels_beyond_u32() {
+        // arrange
+        let region = Region::new(-2.0, 1.0, -1.0, 1.0);
+
+        // act & assert
+        assert!(verify_full_resolution(region, 4095, 20).is_ok());
+        assert!(verify_full_resolution(region, 4096, 20).is_err());
+        assert!(verify_full_resolution(Region::new(-2.0, 1.0, -8.0, 8.0), 1024, 20).is_err());
+    }
+}
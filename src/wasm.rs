@@ -0,0 +1,46 @@
+// JavaScript interface of the renderer for wasm32-unknown-unknown, i.e. built by wasm-pack with the wasm feature.
+// There are no threads in the browser, so the engines render serially.
+use num::complex::Complex64;
+use wasm_bindgen::prelude::*;
+
+use fractal::coloring;
+use fractal::prelude::*;
+
+
+// RGBA pixels of the gray escape times for new ImageData(new Uint8ClampedArray(pixels), width, height),
+// the engine is "default" or "recursive"
+#[wasm_bindgen]
+pub fn render_rgba(center_re: f64, center_im: f64, radius: f64, width: u32, height: u32, max_iterations: u8, engine: &str) -> Result<Vec<u8>, JsValue> {
+    let engine_type = EngineType::all().iter().cloned()
+        .find(|engine_type| engine_type.name().eq_ignore_ascii_case(engine))
+        .ok_or_else(|| JsValue::from_str(&format!("unsupported engine type {}", engine)))?;
+
+    if radius.is_nan() || radius <= 0.0 || width == 0 || height == 0 || max_iterations == 0 {
+        return Err(JsValue::from_str("radius, width, height and max iterations have to be positive"));
+    }
+
+    let image = Renderer::new()
+        .center(Complex64::new(center_re, center_im), radius)
+        .size(width, height)
+        .max_iterations(max_iterations)
+        .engine_type(engine_type)
+        .render();
+
+    Ok(coloring::rgba(&image.pixels))
+}
+
+
+// Center and radius ([re, im, radius]) of a region type of the command line, e.g. "SeaHorseValley"
+#[wasm_bindgen]
+pub fn region_center(name: &str) -> Result<Vec<f64>, JsValue> {
+    let region_type = RegionType::for_name(name).ok_or_else(|| JsValue::from_str(&format!("unsupported region type {}", name)))?;
+    let region = Region::new_for_type(region_type);
+
+    Ok(vec![region.min_re() + region.width() / 2.0, region.min_im() + region.height() / 2.0, region.width().max(region.height()) / 2.0])
+}
+
+
+#[wasm_bindgen]
+pub fn region_names() -> Vec<JsValue> {
+    RegionType::all().iter().map(|region_type| JsValue::from_str(region_type.name())).collect()
+}
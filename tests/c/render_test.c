@@ -0,0 +1,72 @@
+/* Renders through the C interface and checks the results, run by tests/ffi.rs */
+#include <stdio.h>
+#include <string.h>
+
+#include "fractal.h"
+
+#define WIDTH 64
+#define HEIGHT 48
+
+static int failures = 0;
+
+static void check(int condition, const char *description) {
+    if (!condition) {
+        fprintf(stderr, "failed: %s\n", description);
+        failures++;
+    }
+}
+
+static FractalStatus render(FractalEngine *engine, const FractalRegion *region, uint8_t *pixels, FractalStats *stats) {
+    FractalParams params;
+    memset(&params, 0, sizeof(params));
+    params.region = *region;
+    params.width = WIDTH;
+    params.height = HEIGHT;
+    params.max_iterations = 200;
+    params.engine = engine;
+
+    return fractal_render(&params, pixels, WIDTH * HEIGHT, stats);
+}
+
+int main(void) {
+    static uint8_t simple_pixels[WIDTH * HEIGHT];
+    static uint8_t recursive_pixels[WIDTH * HEIGHT];
+    FractalRegion region;
+    FractalStats stats;
+    FractalParams params;
+
+    FractalEngine *simple_engine = fractal_engine_new(FRACTAL_ENGINE_SIMPLE, false, 0);
+    FractalEngine *recursive_engine = fractal_engine_new(FRACTAL_ENGINE_RECURSIVE, true, 16);
+    check(simple_engine != NULL && recursive_engine != NULL, "engines are created");
+    check(fractal_engine_new(FRACTAL_ENGINE_RECURSIVE, false, 0) == NULL, "recursive engine needs band height");
+    check(fractal_engine_new(7, false, 16) == NULL, "unknown engine type is rejected");
+
+    check(fractal_region_for_name("SeaHorseValley", &region) == FRACTAL_OK, "region is found by name");
+    check(fractal_region_for_name("Atlantis", &region) == FRACTAL_UNKNOWN_NAME, "unknown region is rejected");
+    check(fractal_region_for_center(-0.088, 0.654, 0.0, &region) == FRACTAL_INVALID_ARGUMENT, "zero radius is rejected");
+    check(fractal_region_for_center(-0.088, 0.654, 0.0012, &region) == FRACTAL_OK, "region is created for center");
+
+    check(render(simple_engine, &region, simple_pixels, &stats) == FRACTAL_OK, "simple engine renders");
+    check(stats.pixels_inside + stats.pixels_outside == WIDTH * HEIGHT, "stats count every pixel");
+    check(render(recursive_engine, &region, recursive_pixels, NULL) == FRACTAL_OK, "recursive engine renders without stats");
+    check(memcmp(simple_pixels, recursive_pixels, sizeof(simple_pixels)) == 0, "engines render the same pixels");
+
+    memset(&params, 0, sizeof(params));
+    params.region = region;
+    params.width = WIDTH;
+    params.height = HEIGHT;
+    params.max_iterations = 200;
+    params.engine = simple_engine;
+    check(fractal_render(&params, simple_pixels, WIDTH * HEIGHT - 1, NULL) == FRACTAL_BUFFER_TOO_SMALL, "small buffer is rejected");
+    check(fractal_render(NULL, simple_pixels, WIDTH * HEIGHT, NULL) == FRACTAL_NULL_POINTER, "missing parameters are rejected");
+    params.region.min_re = params.region.max_re + 1.0;
+    check(fractal_render(&params, simple_pixels, WIDTH * HEIGHT, NULL) == FRACTAL_INVALID_ARGUMENT, "inverted region is rejected");
+    check(strcmp(fractal_status_message(FRACTAL_BUFFER_TOO_SMALL), "buffer too small") == 0, "status has message");
+    check(strcmp(fractal_status_message(42), "invalid argument") == 0, "unknown status is described");
+
+    fractal_engine_free(simple_engine);
+    fractal_engine_free(recursive_engine);
+    fractal_engine_free(NULL);
+
+    return failures == 0 ? 0 : 1;
+}
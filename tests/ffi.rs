@@ -0,0 +1,104 @@
+extern crate cbindgen;
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+
+// Set this environment variable to rewrite include/fractal.h from src/ffi.rs
+const UPDATE_HEADER_VARIABLE: &str = "FRACTAL_UPDATE_HEADER";
+
+
+fn manifest_directory() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+
+fn read_text(path: &Path) -> String {
+    let mut text = String::new();
+    File::open(path).and_then(|mut file| file.read_to_string(&mut text)).unwrap_or_else(|e| panic!("reading {} failed: {}", path.display(), e));
+    text
+}
+
+
+fn generated_header() -> String {
+    let config = cbindgen::Config::from_file(manifest_directory().join("cbindgen.toml")).expect("reading cbindgen.toml failed");
+    let mut header = Vec::new();
+
+    cbindgen::Builder::new()
+        .with_crate(manifest_directory())
+        .with_config(config)
+        .generate()
+        .expect("generating header failed")
+        .write(&mut header);
+
+    String::from_utf8(header).unwrap()
+}
+
+
+// The tests are built against the rlib, so the shared library is built separately
+fn shared_library_directory() -> PathBuf {
+    let target_directory = manifest_directory().join("target").join("ffi");
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--lib", "--target-dir"])
+        .arg(&target_directory)
+        .current_dir(manifest_directory())
+        .status()
+        .expect("running cargo failed");
+
+    assert!(status.success(), "building shared library failed");
+
+    target_directory.join("debug")
+}
+
+
+#[test]
+fn header_is_up_to_date() {
+    // arrange
+    let header_path = manifest_directory().join("include").join("fractal.h");
+
+    // act
+    let header = generated_header();
+
+    // assert
+    if env::var_os(UPDATE_HEADER_VARIABLE).is_some() {
+        std::fs::write(&header_path, &header).unwrap();
+    }
+
+    assert!(read_text(&header_path) == header, "include/fractal.h is out of date, rerun with {} set", UPDATE_HEADER_VARIABLE);
+}
+
+
+#[test]
+fn c_program_renders_with_shared_library() {
+    // arrange
+    let library_directory = shared_library_directory();
+    let executable = library_directory.join("render_test");
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+    let compiled = Command::new(compiler)
+        .arg("-std=c99")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I").arg(manifest_directory().join("include"))
+        .arg(manifest_directory().join("tests").join("c").join("render_test.c"))
+        .arg("-o").arg(&executable)
+        .arg("-L").arg(&library_directory)
+        .arg("-lfractal")
+        .status()
+        .expect("running C compiler failed");
+
+    assert!(compiled.success(), "compiling tests/c/render_test.c failed");
+
+    // act
+    let output = Command::new(&executable)
+        .env("LD_LIBRARY_PATH", &library_directory)
+        .env("DYLD_LIBRARY_PATH", &library_directory)
+        .output()
+        .expect("running C program failed");
+
+    // assert
+    assert!(output.status.success(), "C program failed: {}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+}
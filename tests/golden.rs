@@ -0,0 +1,230 @@
+extern crate num;
+extern crate fractal;
+
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use num::complex::Complex64;
+use fractal::prelude::*;
+
+
+// Set this environment variable to rewrite the golden files from the default engine
+const UPDATE_GOLDEN_VARIABLE: &str = "FRACTAL_UPDATE_GOLDEN";
+const BAND_HEIGHT: u32 = 16;
+const STREAM_BAND_HEIGHT: u32 = 7;
+const MAX_REPORTED_PIXELS: usize = 20;
+
+
+struct View {
+    name: &'static str,
+    region: Region,
+    width: u32,
+    height: u32,
+    max_iterations: u8,
+}
+
+
+struct EngineMode {
+    name: &'static str,
+    engine: Box<dyn MandelbrotEngine>,
+}
+
+
+fn view(name: &str) -> View {
+    catalog()
+        .into_iter()
+        .find(|view| view.name == name)
+        .expect("view should be part of the catalog")
+}
+
+
+fn catalog() -> Vec<View> {
+    vec![
+        View { name: "default", region: Region::new_for_type(RegionType::Default), width: 96, height: 64, max_iterations: 64 },
+        View { name: "sea_horse_valley", region: Region::new_for_type(RegionType::SeaHorseValley), width: 96, height: 64, max_iterations: 255 },
+        View { name: "elephant_valley", region: Region::new_for_center(Complex64::new(0.3, 0.0), 0.05), width: 96, height: 64, max_iterations: 255 },
+        View { name: "triple_spiral", region: Region::new_for_center(Complex64::new(-0.088, 0.654), 0.01), width: 64, height: 64, max_iterations: 255 },
+        View { name: "odd_size", region: Region::new_for_type(RegionType::Default), width: 77, height: 41, max_iterations: 100 },
+    ]
+}
+
+
+fn engine_modes() -> Vec<EngineMode> {
+    vec![
+        EngineMode { name: "Default (serial)", engine: Box::new(SimpleMandelbrotEngine::new(false)) },
+        EngineMode { name: "Default (parallel)", engine: Box::new(SimpleMandelbrotEngine::new(true)) },
+        EngineMode { name: "Recursive (serial)", engine: Box::new(RecursiveMandelbrotEngine::new(false, BAND_HEIGHT)) },
+        EngineMode { name: "Recursive (parallel)", engine: Box::new(RecursiveMandelbrotEngine::new(true, BAND_HEIGHT)) },
+    ]
+}
+
+
+fn render(view: &View, engine_mode: &EngineMode) -> Vec<u8> {
+    let mandelbrot = Mandelbrot::new(view.region, view.width, view.height, view.max_iterations);
+    let (pixels, _) = mandelbrot.calculate(&*engine_mode.engine);
+
+    pixels
+}
+
+
+fn golden_path(view: &View) -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests");
+    path.push("golden");
+    path.push(format!("{}.pgm", view.name));
+
+    path
+}
+
+
+// Golden buffers are stored as binary PGM files, so they can be inspected with any image viewer
+fn read_golden(view: &View) -> Vec<u8> {
+    let path = golden_path(view);
+    let mut contents: Vec<u8> = Vec::new();
+
+    File::open(&path)
+        .and_then(|mut file| file.read_to_end(&mut contents))
+        .unwrap_or_else(|e| panic!("reading golden file {} failed ({}), run with {}=1 to create it", path.display(), e, UPDATE_GOLDEN_VARIABLE));
+
+    let header = format!("P5\n{} {}\n255\n", view.width, view.height);
+    assert!(contents.starts_with(header.as_bytes()), "golden file {} does not match the view's resolution", path.display());
+
+    contents.split_off(header.len())
+}
+
+
+fn write_golden(view: &View, pixels: &[u8]) {
+    let mut file = File::create(golden_path(view)).unwrap();
+
+    write!(file, "P5\n{} {}\n255\n", view.width, view.height).unwrap();
+    file.write_all(pixels).unwrap();
+}
+
+
+fn differing_pixels(expected: &[u8], actual: &[u8], width: u32) -> Vec<(u32, u32, u8, u8)> {
+    expected.iter()
+        .zip(actual.iter())
+        .enumerate()
+        .filter(|&(_, (expected_escape, actual_escape))| expected_escape != actual_escape)
+        .map(|(index, (&expected_escape, &actual_escape))| {
+            (index as u32 % width, index as u32 / width, expected_escape, actual_escape)
+        })
+        .collect()
+}
+
+
+fn assert_same_escapes(expected: &[u8], actual: &[u8], view: &View, description: &str) {
+    assert_eq!(expected.len(), actual.len(), "{}: {} has a different number of pixels", view.name, description);
+
+    let differences = differing_pixels(expected, actual, view.width);
+
+    if differences.is_empty() {
+        return;
+    }
+
+    let report: Vec<String> = differences.iter()
+        .take(MAX_REPORTED_PIXELS)
+        .map(|&(x, y, expected_escape, actual_escape)| format!("  ({}, {}): expected {}, got {}", x, y, expected_escape, actual_escape))
+        .collect();
+
+    panic!("{}: {} differs in {} of {} pixels:\n{}{}",
+           view.name,
+           description,
+           differences.len(),
+           expected.len(),
+           report.join("\n"),
+           if differences.len() > MAX_REPORTED_PIXELS { "\n  ..." } else { "" });
+}
+
+
+fn assert_view_matches_golden(name: &str) {
+    let view = view(name);
+    let engine_modes = engine_modes();
+
+    if env::var_os(UPDATE_GOLDEN_VARIABLE).is_some() {
+        write_golden(&view, &render(&view, &engine_modes[0]));
+    }
+
+    let golden = read_golden(&view);
+
+    for engine_mode in &engine_modes {
+        let pixels = render(&view, engine_mode);
+        assert_same_escapes(&golden, &pixels, &view, &format!("{} compared to golden file", engine_mode.name));
+    }
+}
+
+
+#[test]
+fn default_view_matches_golden_for_all_engines() {
+    assert_view_matches_golden("default");
+}
+
+#[test]
+fn sea_horse_valley_view_matches_golden_for_all_engines() {
+    assert_view_matches_golden("sea_horse_valley");
+}
+
+#[test]
+fn elephant_valley_view_matches_golden_for_all_engines() {
+    assert_view_matches_golden("elephant_valley");
+}
+
+#[test]
+fn triple_spiral_view_matches_golden_for_all_engines() {
+    assert_view_matches_golden("triple_spiral");
+}
+
+#[test]
+fn odd_size_view_matches_golden_for_all_engines() {
+    assert_view_matches_golden("odd_size");
+}
+
+#[test]
+fn engines_agree_with_each_other_for_all_views() {
+    for view in catalog() {
+        let engine_modes = engine_modes();
+        let reference = render(&view, &engine_modes[0]);
+
+        for engine_mode in engine_modes.iter().skip(1) {
+            let pixels = render(&view, engine_mode);
+            let description = format!("{} compared to {}", engine_mode.name, engine_modes[0].name);
+
+            assert_same_escapes(&reference, &pixels, &view, &description);
+        }
+    }
+}
+
+#[test]
+fn differing_pixels_reports_coordinates_and_escapes() {
+    // arrange
+    let expected: Vec<u8> = vec!(1, 2, 3, 4, 5, 6);
+    let actual: Vec<u8> = vec!(1, 2, 3, 4, 9, 6);
+
+    // act
+    let differences = differing_pixels(&expected, &actual, 3);
+
+    // assert
+    assert_eq!(differences, vec!((1, 1, 5, 9)));
+}
+
+#[test]
+fn streamed_bands_match_golden_for_all_views() {
+    for view in catalog() {
+        let golden = read_golden(&view);
+        let mandelbrot = Mandelbrot::new(view.region, view.width, view.height, view.max_iterations);
+
+        for engine_mode in &engine_modes() {
+            let mut pixels: Vec<u8> = Vec::new();
+            let result: Result<_, ()> = mandelbrot.calculate_bands(&*engine_mode.engine, STREAM_BAND_HEIGHT, |band| {
+                pixels.extend_from_slice(band);
+                Ok(())
+            });
+
+            let stats = result.unwrap();
+
+            assert_eq!(stats.pixels_inside + stats.pixels_outside, golden.len() as u64);
+            assert_same_escapes(&golden, &pixels, &view, &format!("{} streamed compared to golden file", engine_mode.name));
+        }
+    }
+}
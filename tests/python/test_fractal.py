@@ -0,0 +1,85 @@
+# Tests of the Python module, run against a locally built wheel:
+#   pip install maturin && maturin build --release && pip install target/wheels/fractal-*.whl numpy pytest
+#   pytest tests/python
+import threading
+
+import numpy as np
+import pytest
+
+import fractal
+
+
+def test_render_returns_escape_times_of_every_pixel():
+    # act
+    pixels = fractal.render(-0.5 + 0j, 1.5, 64, 48, max_iterations=100)
+
+    # assert
+    assert pixels.shape == (48, 64)
+    assert pixels.dtype == np.uint8
+    assert pixels.max() == 100
+    assert pixels.min() < 10
+
+
+def test_engines_render_the_same_pixels():
+    # arrange
+    center = complex(-0.74548, 0.11669)
+
+    # act
+    simple = fractal.render(center, 0.01276, 80, 60, engine="default")
+    recursive = fractal.render(center, 0.01276, 80, 60, engine="recursive", parallel=True)
+
+    # assert
+    assert np.array_equal(simple, recursive)
+
+
+def test_render_region_matches_render_around_center():
+    # arrange
+    region = fractal.Region.for_center(-0.088 + 0.654j, 0.0012)
+
+    # act & assert
+    assert np.array_equal(fractal.render_region(region, 32, 32), fractal.render(-0.088 + 0.654j, 0.0012, 32, 32))
+
+
+def test_region_types_match_names_of_command_line():
+    # act
+    region = fractal.Region.for_type(fractal.RegionType.SeaHorseValley)
+
+    # assert
+    assert repr(region) == repr(fractal.Region.for_name("SeaHorseValley"))
+    assert region.min_re < -0.74548 < region.max_re
+    assert region.min_im < 0.11669 < region.max_im
+    assert fractal.RegionType.SeaHorseValley.description
+
+
+def test_invalid_arguments_raise_value_error():
+    # act & assert
+    with pytest.raises(ValueError):
+        fractal.render(0j, 1.0, 8, 8, engine="fast")
+
+    with pytest.raises(ValueError):
+        fractal.render(0j, -1.0, 8, 8)
+
+    with pytest.raises(ValueError):
+        fractal.Region.for_name("Atlantis")
+
+
+def test_rendering_releases_gil():
+    # arrange
+    ticks = []
+    done = threading.Event()
+
+    def tick():
+        while not done.is_set():
+            ticks.append(1)
+            done.wait(0.001)
+
+    thread = threading.Thread(target=tick)
+    thread.start()
+
+    # act
+    fractal.render(-0.743643887037151 + 0.13182590420533j, 0.00003, 800, 600, max_iterations=255)
+    done.set()
+    thread.join()
+
+    # assert
+    assert len(ticks) > 1